@@ -0,0 +1,205 @@
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand_chacha::ChaCha8Rng;
+
+use crate::checkpoint::OperatorState;
+
+/// How an adaptive selector assigns credit and chooses between operators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    /// Adaptive pursuit: the operator with the best estimated quality has
+    /// its selection probability pushed towards a maximum, all others
+    /// towards a minimum.
+    Pursuit,
+    /// UCB1 multi-armed bandit: picks the operator maximising its average
+    /// credit, normalised by the best average, plus an exploration bonus
+    /// that shrinks as it is used.
+    Bandit,
+}
+
+impl Strategy {
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        match name.trim() {
+            "pursuit" => Ok(Self::Pursuit),
+            "bandit" => Ok(Self::Bandit),
+            _ => Err("unknown operator selection strategy"),
+        }
+    }
+}
+
+/// Learning rate for the quality estimates used by adaptive pursuit.
+const ALPHA: f64 = 0.1;
+/// Rate at which pursuit moves selection probabilities.
+const BETA: f64 = 0.1;
+/// Exploration weight of the UCB1 bonus.
+const EXPLORATION: f64 = 0.5;
+
+/// Chooses between a fixed set of operators, learning from the credit each
+/// one earns which of them is currently producing improvements.
+pub struct OperatorSelector<T> {
+    operators: Vec<T>,
+    strategy: Strategy,
+    quality: Vec<f64>,
+    probability: Vec<f64>,
+    uses: Vec<usize>,
+    total_credit: Vec<f64>,
+}
+
+impl<T: Copy + PartialEq> OperatorSelector<T> {
+    pub fn new(operators: &[T], strategy: Strategy) -> Self {
+        let num_operators = operators.len();
+        if num_operators == 0 {
+            panic!("operator selector requires at least one operator");
+        }
+
+        Self {
+            operators: operators.to_vec(),
+            strategy,
+            quality: vec![0.0; num_operators],
+            probability: vec![1.0 / num_operators as f64; num_operators],
+            uses: vec![0; num_operators],
+            total_credit: vec![0.0; num_operators],
+        }
+    }
+
+    /// Returns the operator to use next.
+    pub fn select(&mut self, rng: &mut ChaCha8Rng) -> T {
+        let idx = match self.strategy {
+            Strategy::Pursuit => WeightedIndex::new(&self.probability)
+                .unwrap()
+                .sample(rng),
+            Strategy::Bandit => self.upper_confidence_bound(),
+        };
+        self.uses[idx] += 1;
+        self.operators[idx]
+    }
+
+    /// Assigns the credit earned by an operator, which should be
+    /// non-negative with larger values meaning larger improvements.
+    /// Panics if the operator is not one of the selector's.
+    pub fn reward(&mut self, op: T, credit: f64) {
+        let idx = self.operators
+            .iter()
+            .position(|&x| x == op)
+            .expect("operator not known to selector");
+        self.total_credit[idx] += credit;
+        match self.strategy {
+            Strategy::Pursuit => {
+                self.quality[idx] += ALPHA * (credit - self.quality[idx]);
+                self.pursue_best();
+            }
+            Strategy::Bandit => {
+                self.quality[idx] = self.total_credit[idx] / self.uses[idx] as f64;
+            }
+        }
+    }
+
+    /// Returns each operator with its number of uses, mean credit per use
+    /// and current selection probability (for the bandit, its share of uses).
+    pub fn stats(&self) -> Vec<(T, usize, f64, f64)> {
+        let total_uses = self.uses.iter().sum::<usize>().max(1) as f64;
+        (0..self.operators.len())
+            .map(|idx| {
+                let uses = self.uses[idx];
+                let mean_credit = if uses == 0 {
+                    0.0
+                } else {
+                    self.total_credit[idx] / uses as f64
+                };
+                let probability = match self.strategy {
+                    Strategy::Pursuit => self.probability[idx],
+                    Strategy::Bandit => uses as f64 / total_uses,
+                };
+                (self.operators[idx], uses, mean_credit, probability)
+            })
+            .collect()
+    }
+
+    /// Returns each operator's quality, probability, uses and total credit,
+    /// as saved in a checkpoint.
+    pub fn state(&self) -> Vec<OperatorState> {
+        (0..self.operators.len())
+            .map(|idx| {
+                (self.quality[idx], self.probability[idx], self.uses[idx], self.total_credit[idx])
+            })
+            .collect()
+    }
+
+    /// Restores the state saved by `state`.
+    /// Panics if it is not for the same number of operators.
+    pub fn restore(&mut self, state: &[OperatorState]) {
+        assert_eq!(self.operators.len(), state.len(), "operator state of wrong length");
+        for (idx, &(quality, probability, uses, credit)) in state.iter().enumerate() {
+            self.quality[idx] = quality;
+            self.probability[idx] = probability;
+            self.uses[idx] = uses;
+            self.total_credit[idx] = credit;
+        }
+    }
+
+    fn pursue_best(&mut self) {
+        let num_operators = self.operators.len() as f64;
+        let p_min = 0.1 / num_operators;
+        let p_max = 1.0 - (num_operators - 1.0) * p_min;
+
+        let best = argmax(&self.quality);
+        for (idx, p) in self.probability.iter_mut().enumerate() {
+            let target = if idx == best { p_max } else { p_min };
+            *p += BETA * (target - *p);
+        }
+    }
+
+    fn upper_confidence_bound(&self) -> usize {
+        if let Some(unused) = self.uses.iter().position(|&uses| uses == 0) {
+            return unused;
+        }
+
+        let total_uses = self.uses.iter().sum::<usize>() as f64;
+        let best_quality = self.quality[argmax(&self.quality)];
+        let scores = self.quality
+            .iter()
+            .zip(&self.uses)
+            .map(|(&quality, &uses)| {
+                let quality = if best_quality > 0.0 {
+                    quality / best_quality
+                } else {
+                    0.0
+                };
+                quality + EXPLORATION * (2.0 * total_uses.ln() / uses as f64).sqrt()
+            })
+            .collect::<Vec<f64>>();
+        argmax(&scores)
+    }
+}
+
+fn argmax(values: &[f64]) -> usize {
+    let mut best = 0;
+    for (idx, &value) in values.iter().enumerate() {
+        if value > values[best] {
+            best = idx;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectors_favor_the_rewarded_operator() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        for strategy in [Strategy::Pursuit, Strategy::Bandit] {
+            let mut selector = OperatorSelector::new(&["good", "bad"], strategy);
+            for _ in 0..1000 {
+                let op = selector.select(&mut rng);
+                selector.reward(op, if op == "good" { 1.0 } else { 0.0 });
+            }
+
+            let stats = selector.stats();
+            assert!(stats[0].1 > stats[1].1);
+            assert!(stats[0].3 > 0.8);
+            assert_eq!(1.0, stats[0].2);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use rand::prelude::*;
+
+use crate::config::Config;
+use crate::diversity::Diversity;
+use crate::observer::{Event, Observer, PhaseChange, Statistics};
+use crate::result::RunResult;
+use crate::solver::Budget;
+use crate::termination::StopReason;
+use crate::weighted_graph::WeightedGraph;
+
+/// Moves sampled from the initial tour to choose the starting temperature.
+const TEMPERATURE_SAMPLES: usize = 100;
+/// Fraction of uphill moves accepted at the starting temperature.
+const INITIAL_ACCEPTANCE: f64 = 0.5;
+
+/// How the temperature falls over a run. The temperature is updated after
+/// every epoch of as many moves as there are cities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cooling {
+    /// Multiplies the temperature by alpha after every epoch.
+    Geometric { alpha: f64 },
+    /// Raises or lowers the temperature to track a target acceptance rate
+    /// that falls from `acceptance` to 0 over the run.
+    Adaptive { acceptance: f64 },
+    /// Cools geometrically, but reheats once the best tour has not improved
+    /// for `stall` evaluations, each time to half the previous reheat.
+    Reheating { alpha: f64, stall: usize },
+}
+
+impl Default for Cooling {
+    /// Adaptive cooling, as it suits any budget.
+    fn default() -> Self {
+        Self::Adaptive { acceptance: 0.5 }
+    }
+}
+
+impl Cooling {
+    /// Parses a schedule of the form `geometric:<alpha>`,
+    /// `adaptive:<acceptance>` or `reheating:<alpha>:<stall>`.
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let mut parts = spec.trim().split(':');
+        let name = parts.next().unwrap_or("");
+        let params = parts
+            .map(|param| param.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| "could not parse cooling schedule parameter")?;
+
+        match (name, params.as_slice()) {
+            ("geometric", &[alpha]) if alpha > 0.0 && alpha < 1.0 =>
+                Ok(Self::Geometric { alpha }),
+            ("adaptive", &[acceptance]) if acceptance > 0.0 && acceptance <= 1.0 =>
+                Ok(Self::Adaptive { acceptance }),
+            ("reheating", &[alpha, stall]) if alpha > 0.0 && alpha < 1.0 && stall >= 1.0 =>
+                Ok(Self::Reheating { alpha, stall: stall as usize }),
+            _ => Err("unknown cooling schedule"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Geometric { .. } => "geometric",
+            Self::Adaptive { .. } => "adaptive",
+            Self::Reheating { .. } => "reheating",
+        }
+    }
+}
+
+/// The temperature of a run, along with what the cooling schedule needs to
+/// update it.
+struct Temperature {
+    cooling: Cooling,
+    current: f64,
+    reheat_to: f64,
+    last_reheat: usize,
+}
+
+impl Temperature {
+    fn new(cooling: Cooling, initial: f64) -> Self {
+        Self {
+            cooling,
+            current: initial,
+            reheat_to: initial,
+            last_reheat: 0,
+        }
+    }
+
+    /// Updates the temperature at the end of an epoch, given the fraction of
+    /// moves accepted during it. Returns true if the run was reheated.
+    fn update(&mut self, acceptance: f64, progress: f64,
+              evals: usize, last_improvement: usize) -> bool {
+        match self.cooling {
+            Cooling::Geometric { alpha } => self.current *= alpha,
+            Cooling::Adaptive { acceptance: initial } => {
+                let target = initial * (1.0 - progress.min(1.0)).powi(2);
+                self.current *= if acceptance < target { 1.1 } else { 0.9 };
+            }
+            Cooling::Reheating { alpha, stall } => {
+                let since = evals - last_improvement.max(self.last_reheat);
+                if since >= stall {
+                    self.reheat_to /= 2.0;
+                    self.current = self.reheat_to;
+                    self.last_reheat = evals;
+                    return true;
+                }
+                self.current *= alpha;
+            }
+        }
+        false
+    }
+}
+
+/// Runs simulated annealing on the given graph from a single tour, built as
+/// the GA would build the first member of its initial population. Each
+/// evaluation proposes one move made by a randomly chosen operator from
+/// `config.moves`, priced by the change in length of the edges it touches.
+/// Downhill moves are always accepted, uphill moves with probability
+/// exp(-delta / temperature).
+pub fn anneal(config: &Config, graph: &WeightedGraph, budget: &Budget,
+              observer: &mut dyn Observer) -> RunResult {
+    let mut rng = config.rng();
+    let num_alleles = graph.num_vertices();
+    let num_evals = budget.num_evals;
+
+    let mut current = crate::initial_population(
+        1, num_alleles, &config.seeding, graph, &mut rng
+    ).remove(0);
+    let mut best = current.clone();
+
+    // Choose the starting temperature so that about half of the uphill moves
+    // from the initial tour would be accepted.
+    let uphill = (0..TEMPERATURE_SAMPLES)
+        .map(|_| {
+            let mutation = *config.moves.choose(&mut rng).unwrap();
+            current.0.move_delta(mutation.random_move(num_alleles, &mut rng), graph)
+        })
+        .filter(|&delta| delta > 0.0)
+        .collect::<Vec<f64>>();
+    let initial_temperature = match uphill.len() {
+        0 => 1.0,
+        len => uphill.iter().sum::<f64>() / len as f64 / -INITIAL_ACCEPTANCE.ln(),
+    };
+    let mut temperature = Temperature::new(config.cooling, initial_temperature);
+
+    let criteria = crate::termination_criteria(config, budget, graph, best.1, observer);
+    let mut termination = criteria.start(num_evals, best.1);
+    crate::notify_new_best(observer, &mut termination, 0, &best);
+    let epoch = num_alleles.max(1);
+    let mut epoch_accepted = 0;
+    let mut last_improvement = 0;
+    let mut evals = 0;
+    let stop_reason: StopReason;
+    loop {
+        if let Some(reason) = termination.check(evals, best.1) {
+            stop_reason = reason;
+            break;
+        }
+
+        let mutation = *config.moves.choose(&mut rng).unwrap();
+        let mv = mutation.random_move(num_alleles, &mut rng);
+        let delta = current.0.move_delta(mv, graph);
+        evals += 1;
+
+        if delta <= 0.0 || rng.gen::<f64>() < (-delta / temperature.current).exp() {
+            current.0.apply_move(mv);
+            current.1 += delta;
+            epoch_accepted += 1;
+            if current.1 < best.1 {
+                // Recompute the length of new best tours so that rounding
+                // errors in the deltas do not accumulate.
+                current.1 = crate::fitness(graph, current.0.data());
+                best = current.clone();
+                last_improvement = evals;
+                crate::notify_new_best(observer, &mut termination, evals, &best);
+            }
+        }
+
+        if evals % epoch == 0 {
+            let acceptance = epoch_accepted as f64 / epoch as f64;
+            epoch_accepted = 0;
+            if temperature.update(acceptance, termination.progress(evals),
+                                  evals, last_improvement) {
+                let change = PhaseChange::Reheat { temperature: temperature.current };
+                crate::notify(observer, &mut termination, Event::PhaseChange { evals, change });
+            }
+        }
+
+        if evals % 10 == 0 {
+            let best = best.1;
+            crate::notify(observer, &mut termination, Event::Progress { evals, best });
+        }
+        let interval = config.diversity_interval;
+        if interval > 0 && evals % interval == 0 {
+            let statistics = Statistics::Annealing {
+                temperature: temperature.current,
+                current: current.1,
+            };
+            crate::notify(observer, &mut termination, Event::Statistics { evals, statistics });
+        }
+    }
+
+    crate::notify_stop(observer, evals, stop_reason, best.1, criteria.lower_bound);
+
+    RunResult {
+        best_tour: best.0.data().to_vec(),
+        best_length: best.1,
+        evaluations: evals,
+        elapsed: termination.elapsed(),
+        stop_reason,
+        diversity: Diversity::measure(&[best]),
+        lower_bound: criteria.lower_bound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::Silent;
+    use crate::weighted_graph;
+
+    #[test]
+    fn cooling_schedules_are_parsed() {
+        assert_eq!(Ok(Cooling::Geometric { alpha: 0.9 }), Cooling::parse("geometric:0.9"));
+        assert_eq!(Ok(Cooling::Adaptive { acceptance: 0.4 }), Cooling::parse("adaptive:0.4"));
+        assert_eq!(
+            Ok(Cooling::Reheating { alpha: 0.95, stall: 500 }),
+            Cooling::parse("reheating:0.95:500")
+        );
+        assert!(Cooling::parse("geometric:1.5").is_err());
+        assert!(Cooling::parse("reheating:0.95").is_err());
+    }
+
+    #[test]
+    fn annealing_improves_on_a_random_tour() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(30, 1));
+
+        for cooling in ["geometric:0.9", "adaptive:0.5", "reheating:0.9:300"] {
+            let args = ["tsp", "unused", "2", "20000", "--cooling", cooling, "--seed", "1"];
+            let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            let budget = Budget::from_config(&config);
+            let result = anneal(&config, &graph, &budget, &mut Silent);
+
+            assert_eq!(20000, result.evaluations);
+            assert!((crate::fitness(&graph, &result.best_tour) - result.best_length).abs() < 1e-6);
+            // A random tour of 30 uniform points is about 1500 long, and the
+            // optimum about 450.
+            assert!(result.best_length < 700.0);
+        }
+    }
+}
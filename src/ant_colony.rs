@@ -0,0 +1,283 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::config::Config;
+use crate::construction;
+use crate::diversity::Diversity;
+use crate::genotype::Genotype;
+use crate::observer::{Event, Observer, Statistics};
+use crate::result::RunResult;
+use crate::solver::Budget;
+use crate::termination::StopReason;
+use crate::weighted_graph::WeightedGraph;
+
+/// Weight of distance relative to pheromone when choosing the next city.
+const BETA: f64 = 2.0;
+/// Probability that an ACS ant takes the best looking edge outright.
+const ACS_EXPLOITATION: f64 = 0.9;
+/// Evaporation rates of the global pheromone updates.
+const ACS_EVAPORATION: f64 = 0.1;
+const MMAS_EVAPORATION: f64 = 0.02;
+/// Evaporation rate of the ACS update made as each ant crosses an edge.
+const ACS_LOCAL_EVAPORATION: f64 = 0.1;
+/// MMAS deposits pheromone on the best tour so far, rather than the best
+/// tour of the iteration, once every this many iterations.
+const MMAS_BEST_SO_FAR_INTERVAL: usize = 25;
+
+/// The variant of ant colony optimization to run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AntSystem {
+    /// Ant Colony System: ants mostly take the best looking edge, wear away
+    /// pheromone on the edges they cross, and only the best tour so far
+    /// deposits pheromone.
+    Acs,
+    /// MAX-MIN Ant System: ants choose edges at random in proportion to
+    /// their appeal, all pheromone evaporates, only the best tour of each
+    /// iteration deposits, and pheromone is kept within bounds.
+    MaxMin,
+}
+
+impl AntSystem {
+    pub const ALL: [Self; 2] = [Self::Acs, Self::MaxMin];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|system| system.name() == name.trim())
+            .ok_or("unknown ant system")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Acs => "acs",
+            Self::MaxMin => "mmas",
+        }
+    }
+}
+
+/// The pheromone on every edge, kept symmetric.
+struct Pheromone {
+    values: Vec<f64>,
+    num_vertices: usize,
+}
+
+impl Pheromone {
+    fn new(num_vertices: usize, initial: f64) -> Self {
+        Self {
+            values: vec![initial; num_vertices * num_vertices],
+            num_vertices,
+        }
+    }
+
+    fn get(&self, v: usize, u: usize) -> f64 {
+        self.values[v * self.num_vertices + u]
+    }
+
+    fn set(&mut self, v: usize, u: usize, value: f64) {
+        self.values[v * self.num_vertices + u] = value;
+        self.values[u * self.num_vertices + v] = value;
+    }
+
+    /// Evaporates pheromone on the tour's edges at the given rate, then adds
+    /// deposit times that rate, as in the ACS update.
+    fn reinforce(&mut self, tour: &[usize], rate: f64, deposit: f64) {
+        for (i, &v) in tour.iter().enumerate() {
+            let u = tour[(i + 1) % tour.len()];
+            self.set(v, u, (1.0 - rate) * self.get(v, u) + rate * deposit);
+        }
+    }
+}
+
+/// Runs ant colony optimization on the given graph with `config.pop_size`
+/// ants. Ants build tours city by city, choosing among the unvisited
+/// cities in the current city's candidate list, or the best looking
+/// unvisited city if there are none. Each tour built, after the optional
+/// local search, costs one evaluation.
+pub fn ant_colony(config: &Config, graph: &WeightedGraph, budget: &Budget,
+                  observer: &mut dyn Observer) -> RunResult {
+    let mut rng = config.rng();
+    let num_vertices = graph.num_vertices();
+    let num_evals = budget.num_evals;
+    let system = config.ant_system;
+    let candidates = graph.nearest_neighbors(config.candidates);
+
+    // The appeal of each edge apart from its pheromone.
+    let mut heuristic = vec![0.0; num_vertices * num_vertices];
+    for v in 0..num_vertices {
+        for u in 0..num_vertices {
+            let distance = graph.weight_between(v, u).max(f64::EPSILON);
+            heuristic[v * num_vertices + u] = distance.powf(-BETA);
+        }
+    }
+
+    // The nearest neighbor tour sets the initial pheromone, and is the best
+    // tour until the ants find a better one.
+    let nearest_neighbor = Genotype::from_data(construction::nearest_neighbor(graph, 0));
+    let nearest_neighbor_length = crate::fitness(graph, nearest_neighbor.data());
+    let initial_pheromone = match system {
+        AntSystem::Acs => 1.0 / (num_vertices as f64 * nearest_neighbor_length),
+        AntSystem::MaxMin => 1.0 / (MMAS_EVAPORATION * nearest_neighbor_length),
+    };
+    let mut pheromone = Pheromone::new(num_vertices, initial_pheromone);
+    let mut best = (nearest_neighbor, nearest_neighbor_length);
+
+    let criteria = crate::termination_criteria(config, budget, graph, best.1, observer);
+    let mut termination = criteria.start(num_evals, best.1);
+    crate::notify_new_best(observer, &mut termination, 0, &best);
+    let mut colony: Vec<(Genotype, f64)> = Vec::new();
+    let mut iterations = 0;
+    let mut evals = 0;
+    let stop_reason: StopReason;
+    loop {
+        if let Some(reason) = termination.check(evals, best.1) {
+            stop_reason = reason;
+            break;
+        }
+
+        let num_ants = config.pop_size.min(num_evals - evals);
+        colony.clear();
+        for _ in 0..num_ants {
+            let mut tour = build_tour(
+                system, graph, &candidates, &heuristic, &mut pheromone,
+                initial_pheromone, &mut rng
+            );
+            if let Some(search) = config.local_search {
+                search.apply(&mut tour, graph, &candidates);
+            }
+            let length = crate::fitness(graph, &tour);
+            colony.push((Genotype::from_data(tour), length));
+        }
+        colony.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+        let prev_evals = evals;
+        evals += num_ants;
+        iterations += 1;
+        if colony[0].1 < best.1 {
+            best = colony[0].clone();
+            crate::notify_new_best(observer, &mut termination, evals, &best);
+        }
+
+        match system {
+            AntSystem::Acs => {
+                pheromone.reinforce(best.0.data(), ACS_EVAPORATION, 1.0 / best.1);
+            }
+            AntSystem::MaxMin => {
+                let depositor = match iterations % MMAS_BEST_SO_FAR_INTERVAL {
+                    0 => &best,
+                    _ => &colony[0],
+                };
+                for value in pheromone.values.iter_mut() {
+                    *value *= 1.0 - MMAS_EVAPORATION;
+                }
+                let tour = depositor.0.data();
+                for (i, &v) in tour.iter().enumerate() {
+                    let u = tour[(i + 1) % num_vertices];
+                    pheromone.set(v, u, pheromone.get(v, u) + 1.0 / depositor.1);
+                }
+                let max = 1.0 / (MMAS_EVAPORATION * best.1);
+                let min = max / (2.0 * num_vertices as f64);
+                for value in pheromone.values.iter_mut() {
+                    *value = value.clamp(min, max);
+                }
+            }
+        }
+
+        if evals / 10 > prev_evals / 10 {
+            let best = best.1;
+            crate::notify(observer, &mut termination, Event::Progress { evals, best });
+        }
+        let interval = config.diversity_interval;
+        if interval > 0 && evals / interval > prev_evals / interval {
+            let diversity = Diversity::measure(&colony);
+            let statistics = Statistics::Population(&diversity);
+            crate::notify(observer, &mut termination, Event::Statistics { evals, statistics });
+        }
+    }
+
+    crate::notify_stop(observer, evals, stop_reason, best.1, criteria.lower_bound);
+
+    RunResult {
+        best_tour: best.0.data().to_vec(),
+        best_length: best.1,
+        evaluations: evals,
+        elapsed: termination.elapsed(),
+        stop_reason,
+        diversity: Diversity::measure(&colony),
+        lower_bound: criteria.lower_bound,
+    }
+}
+
+/// Builds one ant's tour from a random starting city.
+fn build_tour(system: AntSystem, graph: &WeightedGraph, candidates: &[Vec<usize>],
+              heuristic: &[f64], pheromone: &mut Pheromone, initial_pheromone: f64,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    let appeal = |pheromone: &Pheromone, v: usize, u: usize| {
+        pheromone.get(v, u) * heuristic[v * num_vertices + u]
+    };
+
+    let mut visited = vec![false; num_vertices];
+    let mut tour = Vec::with_capacity(num_vertices);
+    let mut current = rng.gen_range(0..num_vertices);
+    visited[current] = true;
+    tour.push(current);
+
+    while tour.len() < num_vertices {
+        let options = candidates[current]
+            .iter()
+            .copied()
+            .filter(|&u| !visited[u])
+            .collect::<Vec<usize>>();
+        let by_appeal = |&x: &usize, &y: &usize| {
+            appeal(pheromone, current, x).partial_cmp(&appeal(pheromone, current, y)).unwrap()
+        };
+
+        let next = if options.is_empty() {
+            (0..num_vertices).filter(|&u| !visited[u]).max_by(by_appeal).unwrap()
+        } else if system == AntSystem::Acs && rng.gen::<f64>() < ACS_EXPLOITATION {
+            options.iter().copied().max_by(by_appeal).unwrap()
+        } else {
+            *options
+                .choose_weighted(rng, |&u| appeal(pheromone, current, u))
+                .unwrap_or(&options[0])
+        };
+
+        if system == AntSystem::Acs {
+            let worn = (1.0 - ACS_LOCAL_EVAPORATION) * pheromone.get(current, next)
+                + ACS_LOCAL_EVAPORATION * initial_pheromone;
+            pheromone.set(current, next, worn);
+        }
+        visited[next] = true;
+        tour.push(next);
+        current = next;
+    }
+    tour
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::Silent;
+    use crate::weighted_graph;
+
+    #[test]
+    fn colonies_are_no_worse_than_nearest_neighbor() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(40, 1));
+        let nearest_neighbor = crate::fitness(&graph, &construction::nearest_neighbor(&graph, 0));
+
+        for system in ["acs", "mmas"] {
+            for local_search in ["none", "2-opt"] {
+                let args = [
+                    "tsp", "unused", "10", "2000", "--seed", "1",
+                    "--ant_system", system, "--local_search", local_search,
+                ];
+                let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+                let budget = Budget::from_config(&config);
+                let result = ant_colony(&config, &graph, &budget, &mut Silent);
+
+                assert_eq!(2000, result.evaluations);
+                assert!(result.best_length <= nearest_neighbor);
+                assert!((crate::fitness(&graph, &result.best_tour) - result.best_length).abs() < 1e-6);
+            }
+        }
+    }
+}
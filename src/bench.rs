@@ -0,0 +1,271 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::file_parser;
+use crate::observer::{Event, Signal};
+use crate::result::RunResult;
+use crate::solver::{Budget, Solver};
+use crate::termination;
+use crate::weighted_graph::WeightedGraph;
+
+/// An instance to benchmark, named by its file name without the extension.
+pub struct Instance {
+    pub name: String,
+    pub graph: WeightedGraph,
+}
+
+/// A named configuration, given as the command line arguments that follow
+/// the file name: the pop size, the number of evaluations and any options.
+pub struct Setup {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// The outcome of one run, with the time and evaluations it took to come
+/// within the target gap of the optimum, if it did.
+pub struct Run {
+    pub length: f64,
+    pub evals: usize,
+    pub seconds: f64,
+    pub to_target: Option<(f64, usize)>,
+}
+
+/// Statistics over the runs of one setup on one instance.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub instance: String,
+    pub setup: String,
+    pub runs: usize,
+    pub best: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub optimum: Option<f64>,
+    /// Percentages by which the best and mean lengths exceed the optimum.
+    pub best_gap: Option<f64>,
+    pub mean_gap: Option<f64>,
+    /// Runs that came within the target gap of the optimum.
+    pub hits: usize,
+    /// Mean seconds and evaluations those runs took to do so.
+    pub time_to_target: Option<f64>,
+    pub evals_to_target: Option<f64>,
+    pub mean_evals: f64,
+    pub mean_seconds: f64,
+}
+
+/// Reads every instance in the directory, in order of file name, skipping
+/// files that are not in the instance format.
+pub fn read_instances(dir: &str) -> Result<Vec<Instance>, &'static str> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|_| "error trying to read instance directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    Ok(paths
+        .iter()
+        .filter_map(|path| {
+            let points = file_parser::parse_file(path.to_str()?).ok()?;
+            Some(Instance { name: instance_name(path), graph: WeightedGraph::from_points(points) })
+        })
+        .collect())
+}
+
+fn instance_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Reads a solutions file of known optimal tour lengths, one `name : length`
+/// or `name length` pair per line, with blank lines and lines starting with
+/// `#` ignored.
+pub fn read_solutions(file_name: &str) -> Result<BTreeMap<String, f64>, &'static str> {
+    let contents = fs::read_to_string(file_name)
+        .map_err(|_| "error trying to read solutions file")?;
+    parse_solutions(&contents)
+}
+
+fn parse_solutions(contents: &str) -> Result<BTreeMap<String, f64>, &'static str> {
+    let mut solutions = BTreeMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, length) = line.split_once(':')
+            .or_else(|| line.split_once(char::is_whitespace))
+            .ok_or("solutions file lines must be of the form name : length")?;
+        let length = length.trim().parse::<f64>()
+            .map_err(|_| "could not parse optimal tour length")?;
+        solutions.insert(name.trim().to_string(), length);
+    }
+    Ok(solutions)
+}
+
+/// Runs the setup on the instance the given number of times, reporting each
+/// run as it finishes. A seeded setup uses seeds `seed`, `seed + 1`, ... so
+/// that the runs are independent but reproducible. A run reaches its target
+/// once its best tour is within `target_gap` percent of the optimum.
+pub fn bench(instance: &Instance, setup: &Setup, runs: usize, optimum: Option<f64>,
+             target_gap: f64, report: &mut dyn FnMut(usize, &RunResult))
+-> Result<Summary, &'static str> {
+    let target = optimum.map(|optimum| optimum * (1.0 + target_gap / 100.0));
+    let mut outcomes = Vec::with_capacity(runs);
+    for run in 0..runs {
+        let args = ["tsp", &instance.name].into_iter()
+            .map(String::from)
+            .chain(setup.args.iter().cloned());
+        let mut config = Config::from_args(args)?;
+        config.validate_instance(&instance.graph)?;
+        config.seed = config.seed.map(|seed| seed.wrapping_add(run as u64));
+        let budget = Budget::from_config(&config);
+
+        let start = Instant::now();
+        let mut to_target = None;
+        let result = config.solve(&instance.graph, &budget, &mut |event: &Event| {
+            if let Event::NewBest { evals, length, .. } = *event {
+                if to_target.is_none() && target.is_some_and(|target| length <= target) {
+                    to_target = Some((start.elapsed().as_secs_f64(), evals));
+                }
+            }
+            Signal::Continue
+        });
+        report(run, &result);
+        outcomes.push(Run {
+            length: result.best_length,
+            evals: result.evaluations,
+            seconds: start.elapsed().as_secs_f64(),
+            to_target,
+        });
+    }
+    Ok(Summary::new(&instance.name, &setup.name, optimum, &outcomes))
+}
+
+impl Summary {
+    /// Summarises at least one run.
+    pub fn new(instance: &str, setup: &str, optimum: Option<f64>, runs: &[Run]) -> Self {
+        let count = runs.len() as f64;
+        let best = runs.iter().map(|run| run.length).fold(f64::INFINITY, f64::min);
+        let mean = runs.iter().map(|run| run.length).sum::<f64>() / count;
+        let std_dev = match runs.len() {
+            1 => 0.0,
+            _ => (runs.iter().map(|run| (run.length - mean).powi(2)).sum::<f64>()
+                / (count - 1.0)).sqrt(),
+        };
+        let hits = runs.iter().filter_map(|run| run.to_target).collect::<Vec<_>>();
+        let hit_mean = |value: fn(&(f64, usize)) -> f64| match hits.is_empty() {
+            true => None,
+            false => Some(hits.iter().map(value).sum::<f64>() / hits.len() as f64),
+        };
+
+        Self {
+            instance: instance.to_string(),
+            setup: setup.to_string(),
+            runs: runs.len(),
+            best,
+            mean,
+            std_dev,
+            optimum,
+            best_gap: optimum.map(|optimum| termination::gap(best, optimum)),
+            mean_gap: optimum.map(|optimum| termination::gap(mean, optimum)),
+            hits: hits.len(),
+            time_to_target: hit_mean(|&(seconds, _)| seconds),
+            evals_to_target: hit_mean(|&(_, evals)| evals as f64),
+            mean_evals: runs.iter().map(|run| run.evals as f64).sum::<f64>() / count,
+            mean_seconds: runs.iter().map(|run| run.seconds).sum::<f64>() / count,
+        }
+    }
+}
+
+const COLUMNS: [&str; 14] = [
+    "instance", "config", "runs", "best", "mean", "std", "optimum", "best_gap", "mean_gap",
+    "hits", "time_to_target", "evals_to_target", "evals", "seconds",
+];
+
+/// Returns the summaries as CSV with a header row, leaving cells that do not
+/// apply empty.
+pub fn csv(summaries: &[Summary]) -> String {
+    let mut out = COLUMNS.join(",");
+    out.push('\n');
+    for summary in summaries {
+        out.push_str(&cells(summary, "").join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Returns the summaries as a Markdown table, with `-` in cells that do not
+/// apply.
+pub fn markdown(summaries: &[Summary]) -> String {
+    let mut out = format!("| {} |\n", COLUMNS.join(" | "));
+    writeln!(out, "|{}", "---|".repeat(COLUMNS.len())).unwrap();
+    for summary in summaries {
+        writeln!(out, "| {} |", cells(summary, "-").join(" | ")).unwrap();
+    }
+    out
+}
+
+fn cells(summary: &Summary, missing: &str) -> Vec<String> {
+    let optional = |value: Option<f64>, precision: usize| match value {
+        Some(value) => format!("{:.*}", precision, value),
+        None => missing.to_string(),
+    };
+    vec![
+        summary.instance.clone(),
+        summary.setup.clone(),
+        summary.runs.to_string(),
+        format!("{:.2}", summary.best),
+        format!("{:.2}", summary.mean),
+        format!("{:.2}", summary.std_dev),
+        optional(summary.optimum, 2),
+        optional(summary.best_gap, 3),
+        optional(summary.mean_gap, 3),
+        summary.hits.to_string(),
+        optional(summary.time_to_target, 3),
+        optional(summary.evals_to_target, 0),
+        format!("{:.0}", summary.mean_evals),
+        format!("{:.3}", summary.mean_seconds),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_report_spread_gap_and_time_to_target() {
+        let runs = [
+            Run { length: 110.0, evals: 1000, seconds: 2.0, to_target: None },
+            Run { length: 100.0, evals: 1000, seconds: 1.0, to_target: Some((0.5, 400)) },
+            Run { length: 105.0, evals: 800, seconds: 1.5, to_target: Some((1.5, 600)) },
+        ];
+        let summary = Summary::new("a", "b", Some(100.0), &runs);
+
+        assert_eq!(100.0, summary.best);
+        assert_eq!(105.0, summary.mean);
+        assert_eq!(5.0, summary.std_dev);
+        assert_eq!(Some(0.0), summary.best_gap);
+        assert_eq!(Some(5.0), summary.mean_gap);
+        assert_eq!(2, summary.hits);
+        assert_eq!(Some(1.0), summary.time_to_target);
+        assert_eq!(Some(500.0), summary.evals_to_target);
+        assert!((summary.mean_evals - 2800.0 / 3.0).abs() < 1e-9);
+
+        let unknown = Summary::new("a", "b", None, &runs[..1]);
+        assert_eq!(0.0, unknown.std_dev);
+        assert_eq!(None, unknown.mean_gap);
+        let row = "a,b,1,110.00,110.00,0.00,,,,0,,,1000,2.000";
+        assert_eq!(Some(row), csv(&[unknown]).lines().nth(1));
+    }
+
+    #[test]
+    fn solutions_accept_both_separators() {
+        let solutions = parse_solutions("# optima\nberlin52 : 7542\n\neil51 426\n").unwrap();
+        assert_eq!(Some(&7542.0), solutions.get("berlin52"));
+        assert_eq!(Some(&426.0), solutions.get("eil51"));
+        assert!(parse_solutions("berlin52").is_err());
+    }
+}
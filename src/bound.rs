@@ -0,0 +1,182 @@
+use crate::construction;
+use crate::weighted_graph::WeightedGraph;
+
+/// A minimum 1-tree: a minimum spanning tree over every vertex but one,
+/// plus the two cheapest edges joining that vertex to the tree. Every tour is
+/// a 1-tree, so its weight is a lower bound on the optimal tour length.
+pub struct OneTree {
+    pub edges: Vec<(usize, usize)>,
+    pub degrees: Vec<usize>,
+    /// Weight under the weight function the tree was built with.
+    pub weight: f64,
+}
+
+impl OneTree {
+    /// Builds a minimum 1-tree with `special` as the vertex left out of the
+    /// spanning tree, using the given weight function.
+    pub fn build(graph: &WeightedGraph, special: usize,
+                 weight: impl Fn(usize, usize) -> f64) -> Self {
+        let num_vertices = graph.num_vertices();
+        let others = (0..num_vertices)
+            .filter(|&v| v != special)
+            .collect::<Vec<usize>>();
+        let mut edges = graph.minimum_spanning_tree_of(&others, &weight);
+
+        let mut nearest = others.clone();
+        nearest.sort_by(|&x, &y| {
+            weight(special, x).partial_cmp(&weight(special, y)).unwrap()
+        });
+        for &v in nearest.iter().take(2) {
+            edges.push((special, v));
+        }
+
+        let mut degrees = vec![0; num_vertices];
+        let mut total = 0.0;
+        for &(v, u) in edges.iter() {
+            degrees[v] += 1;
+            degrees[u] += 1;
+            total += weight(v, u);
+        }
+
+        Self {
+            edges,
+            degrees,
+            weight: total,
+        }
+    }
+
+    /// Returns true if every vertex has degree 2, in which case the 1-tree
+    /// is a tour.
+    pub fn is_tour(&self) -> bool {
+        self.degrees.iter().all(|&degree| degree == 2)
+    }
+}
+
+/// Computes the Held-Karp lower bound on the optimal tour length. Each vertex
+/// gets a penalty added to the weight of its edges, which leaves the optimal
+/// tour unchanged but alters the minimum 1-tree; subgradient ascent moves the
+/// penalties towards making every vertex degree 2, raising the bound.
+///
+/// The step size is scaled by the gap to an upper bound: the shorter of
+/// upper_bound, the length of a known tour, and a nearest neighbor tour.
+pub fn held_karp_bound(graph: &WeightedGraph, upper_bound: Option<f64>,
+                       max_iterations: usize) -> f64 {
+    HeldKarp::new(graph, upper_bound).run(max_iterations).0
+}
+
+/// State of the subgradient ascent, exposed so that other algorithms can
+/// reuse the penalties it finds.
+pub struct HeldKarp<'a> {
+    graph: &'a WeightedGraph,
+    upper_bound: f64,
+}
+
+impl<'a> HeldKarp<'a> {
+    pub fn new(graph: &'a WeightedGraph, upper_bound: Option<f64>) -> Self {
+        let nearest_neighbor = match graph.num_vertices() {
+            0 => 0.0,
+            _ => crate::fitness(graph, &construction::nearest_neighbor(graph, 0)),
+        };
+        let upper_bound = upper_bound.map_or(nearest_neighbor, |x| x.min(nearest_neighbor));
+        Self { graph, upper_bound }
+    }
+
+    /// Runs subgradient ascent for at most max_iterations, returning the best
+    /// bound found and the penalties that gave it.
+    pub fn run(&self, max_iterations: usize) -> (f64, Vec<f64>) {
+        let graph = self.graph;
+        let num_vertices = graph.num_vertices();
+        if num_vertices < 3 {
+            // The only tour is optimal, so it is its own bound.
+            let bound = match num_vertices {
+                0 => 0.0,
+                _ => crate::fitness(graph, &(0..num_vertices).collect::<Vec<usize>>()),
+            };
+            return (bound, vec![0.0; num_vertices]);
+        }
+
+        let mut penalties = vec![0.0; num_vertices];
+        let mut best = (f64::NEG_INFINITY, penalties.clone());
+        let mut step_scale = 2.0;
+        let mut since_improvement = 0;
+        // Halve the step after this many iterations without improvement.
+        let patience = (num_vertices / 2).max(10);
+
+        for _ in 0..max_iterations {
+            let tree = OneTree::build(graph, 0, |v, u| {
+                graph.weight_between(v, u) + penalties[v] + penalties[u]
+            });
+            let bound = tree.weight - 2.0 * penalties.iter().sum::<f64>();
+
+            if bound > best.0 {
+                best = (bound, penalties.clone());
+                since_improvement = 0;
+            } else {
+                since_improvement += 1;
+                if since_improvement >= patience {
+                    step_scale /= 2.0;
+                    since_improvement = 0;
+                }
+            }
+
+            let norm = tree.degrees
+                .iter()
+                .map(|&degree| (degree as f64 - 2.0).powi(2))
+                .sum::<f64>();
+            if norm == 0.0 || step_scale < 1e-6 {
+                // A 1-tree that is a tour proves the bound is optimal.
+                break;
+            }
+
+            let step = step_scale * (self.upper_bound - bound).max(0.0) / norm;
+            if step == 0.0 {
+                break;
+            }
+            for (penalty, &degree) in penalties.iter_mut().zip(&tree.degrees) {
+                *penalty += step * (degree as f64 - 2.0);
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use rand::prelude::*;
+
+    #[test]
+    fn bound_is_tight_on_a_circle() {
+        let points = (0..12)
+            .map(|i| {
+                let angle = 2.0 * std::f64::consts::PI * i as f64 / 12.0;
+                (angle.cos(), angle.sin())
+            })
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let optimum = crate::fitness(&graph, &(0..12).collect::<Vec<usize>>());
+
+        assert!((held_karp_bound(&graph, None, 100) - optimum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bound_does_not_exceed_best_tour() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let points = (0..30)
+            .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+
+        let tour = construction::christofides(&graph, construction::Matching::Exact);
+        let length = crate::fitness(&graph, &tour);
+        let bound = held_karp_bound(&graph, Some(length), 1000);
+        let mst_weight = graph.minimum_spanning_tree()
+            .iter()
+            .map(|&(v, u)| graph.weight_between(v, u))
+            .sum::<f64>();
+
+        assert!(bound <= length + 1e-9);
+        assert!(bound >= mst_weight);
+    }
+}
@@ -0,0 +1,285 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Write as _;
+use std::fs;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::genotype::Genotype;
+
+/// First line of every checkpoint file, naming its format.
+const HEADER: &str = "tsp-checkpoint 1";
+
+/// The complete state of a GA run between two generations, from which the
+/// run can be resumed exactly as if it had never stopped.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub evals: usize,
+    /// Time the run had taken, which counts towards its time limit.
+    pub elapsed: Duration,
+    pub lower_bound: Option<f64>,
+    pub best: (Genotype, f64),
+    /// The population, sorted by fitness.
+    pub population: Vec<(Genotype, f64)>,
+    pub rng: ChaCha8Rng,
+    /// The generators of the breeding threads.
+    pub breeders: Vec<ChaCha8Rng>,
+    /// The active phase of the schedule, with its best fitness and the
+    /// evaluation at which that last improved.
+    pub schedule: (usize, f64, usize),
+    /// The best fitness, last improvement and last diversity check of the
+    /// stagnation detector.
+    pub stagnation: (f64, usize, usize),
+    /// The best fitness and last improvement seen by the stop conditions.
+    pub termination: (f64, usize),
+    /// The quality, probability, uses and total credit of each crossover and
+    /// mutation operator, if they are selected adaptively.
+    pub operators: Option<(Vec<OperatorState>, Vec<OperatorState>)>,
+}
+
+pub type OperatorState = (f64, f64, usize, f64);
+
+impl Checkpoint {
+    /// Writes the checkpoint to the named file, replacing it only once the
+    /// new one is complete so that a run killed while writing leaves the
+    /// previous checkpoint intact.
+    pub fn write(&self, file_name: &str) -> Result<(), &'static str> {
+        let mut out = String::new();
+        let line = |out: &mut String, key: &str, fields: String| {
+            writeln!(out, "{} {}", key, fields).unwrap();
+        };
+        out.push_str(HEADER);
+        out.push('\n');
+        line(&mut out, "evals", self.evals.to_string());
+        line(&mut out, "elapsed", self.elapsed.as_secs_f64().to_string());
+        if let Some(bound) = self.lower_bound {
+            line(&mut out, "bound", bound.to_string());
+        }
+        line(&mut out, "rng", rng_fields(&self.rng));
+        for breeder in self.breeders.iter() {
+            line(&mut out, "breeder", rng_fields(breeder));
+        }
+        let (phase, best, last_improvement) = self.schedule;
+        line(&mut out, "schedule", format!("{} {} {}", phase, best, last_improvement));
+        let (best, last_improvement, last_check) = self.stagnation;
+        line(&mut out, "stagnation", format!("{} {} {}", best, last_improvement, last_check));
+        let (best, last_improvement) = self.termination;
+        line(&mut out, "termination", format!("{} {}", best, last_improvement));
+        if let Some((crossovers, mutations)) = &self.operators {
+            for (key, operators) in [("crossover", crossovers), ("mutation", mutations)] {
+                for (quality, probability, uses, credit) in operators.iter() {
+                    let fields = format!("{} {} {} {}", quality, probability, uses, credit);
+                    line(&mut out, key, fields);
+                }
+            }
+        }
+        line(&mut out, "best", member_fields(&self.best));
+        for member in self.population.iter() {
+            line(&mut out, "member", member_fields(member));
+        }
+
+        let temporary = format!("{}.tmp", file_name);
+        fs::write(&temporary, out).map_err(|_| "error trying to write checkpoint file")?;
+        fs::rename(&temporary, file_name).map_err(|_| "error trying to write checkpoint file")
+    }
+
+    /// Reads a checkpoint written by `write`.
+    pub fn read(file_name: &str) -> Result<Self, &'static str> {
+        let contents = fs::read_to_string(file_name)
+            .map_err(|_| "error trying to read checkpoint file")?;
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not a checkpoint file");
+        }
+
+        let mut evals = None;
+        let mut elapsed = None;
+        let mut lower_bound = None;
+        let mut rng = None;
+        let mut breeders = Vec::new();
+        let mut schedule = None;
+        let mut stagnation = None;
+        let mut termination = None;
+        let mut crossovers = Vec::new();
+        let mut mutations = Vec::new();
+        let mut best = None;
+        let mut population = Vec::new();
+        for line in lines {
+            let (key, rest) = line.split_once(' ').ok_or("malformed checkpoint line")?;
+            let mut fields = rest.split_whitespace();
+            let fields = &mut fields;
+            match key {
+                "evals" => evals = Some(field(fields)?),
+                "elapsed" => {
+                    elapsed = Some(Duration::try_from_secs_f64(field(fields)?)
+                        .map_err(|_| "malformed checkpoint line")?);
+                }
+                "bound" => lower_bound = Some(field(fields)?),
+                "rng" => rng = Some(read_rng(fields)?),
+                "breeder" => breeders.push(read_rng(fields)?),
+                "schedule" => schedule = Some((field(fields)?, field(fields)?, field(fields)?)),
+                "stagnation" => {
+                    stagnation = Some((field(fields)?, field(fields)?, field(fields)?));
+                }
+                "termination" => termination = Some((field(fields)?, field(fields)?)),
+                "crossover" | "mutation" => {
+                    let operator = (field(fields)?, field(fields)?, field(fields)?, field(fields)?);
+                    match key {
+                        "crossover" => crossovers.push(operator),
+                        _ => mutations.push(operator),
+                    }
+                }
+                "best" => best = Some(read_member(fields)?),
+                "member" => population.push(read_member(fields)?),
+                _ => return Err("unknown checkpoint line"),
+            }
+        }
+
+        if !population.is_sorted_by(|a: &(Genotype, f64), b| a.1 <= b.1) {
+            return Err("checkpoint population is not sorted by fitness");
+        }
+
+        let missing = "incomplete checkpoint file";
+        let evals = evals.ok_or(missing)?;
+        let schedule: (usize, f64, usize) = schedule.ok_or(missing)?;
+        let stagnation: (f64, usize, usize) = stagnation.ok_or(missing)?;
+        let termination: (f64, usize) = termination.ok_or(missing)?;
+        // Improvements and checks are counted in evaluations already made.
+        let counts = [schedule.2, stagnation.1, stagnation.2, termination.1];
+        if counts.iter().any(|&count| count > evals) {
+            return Err("checkpoint counts past its evaluations");
+        }
+        Ok(Self {
+            evals,
+            elapsed: elapsed.ok_or(missing)?,
+            lower_bound,
+            best: best.ok_or(missing)?,
+            population,
+            rng: rng.ok_or(missing)?,
+            breeders,
+            schedule,
+            stagnation,
+            termination,
+            operators: match crossovers.is_empty() && mutations.is_empty() {
+                true => None,
+                false => Some((crossovers, mutations)),
+            },
+        })
+    }
+}
+
+/// Parses the next field of a checkpoint line.
+fn field<'a, T: FromStr>(fields: &mut impl Iterator<Item = &'a str>) -> Result<T, &'static str> {
+    fields.next()
+        .and_then(|field| field.parse::<T>().ok())
+        .ok_or("malformed checkpoint line")
+}
+
+/// A generator is saved as its seed, stream and position in the stream.
+fn rng_fields(rng: &ChaCha8Rng) -> String {
+    let seed = rng.get_seed().iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    format!("{} {} {}", seed, rng.get_stream(), rng.get_word_pos())
+}
+
+fn read_rng<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<ChaCha8Rng, &'static str> {
+    let hex: String = field(fields)?;
+    if hex.len() != 64 {
+        return Err("malformed checkpoint line");
+    }
+    let mut seed = [0; 32];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| "malformed checkpoint line")?;
+    }
+    let mut rng = ChaCha8Rng::from_seed(seed);
+    rng.set_stream(field(fields)?);
+    rng.set_word_pos(field(fields)?);
+    Ok(rng)
+}
+
+/// A member is saved as its fitness followed by its tour.
+fn member_fields((genotype, fitness): &(Genotype, f64)) -> String {
+    let mut fields = fitness.to_string();
+    for city in genotype.data() {
+        write!(fields, " {}", city).unwrap();
+    }
+    fields
+}
+
+fn read_member<'a>(fields: &mut impl Iterator<Item = &'a str>)
+-> Result<(Genotype, f64), &'static str> {
+    let fitness = field(fields)?;
+    let tour = fields
+        .map(|city| city.parse::<usize>().map_err(|_| "malformed checkpoint line"))
+        .collect::<Result<Vec<usize>, &'static str>>()?;
+    if !Genotype::is_permutation(&tour) {
+        return Err("malformed checkpoint line");
+    }
+    Ok((Genotype::from_data(tour), fitness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn checkpoints_are_read_back_exactly() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let member = |rng: &mut ChaCha8Rng| (Genotype::random(12, rng), rng.gen::<f64>() * 1e6);
+        let mut population = (0..4).map(|_| member(&mut rng)).collect::<Vec<_>>();
+        population.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let mut breeder = ChaCha8Rng::from_rng(&mut rng).unwrap();
+        breeder.set_stream(5);
+        breeder.gen::<u32>();
+        let checkpoint = Checkpoint {
+            evals: 12345,
+            elapsed: Duration::from_secs_f64(1.5),
+            lower_bound: Some(1.0 / 3.0),
+            best: member(&mut rng),
+            population,
+            rng: rng.clone(),
+            breeders: vec![breeder],
+            schedule: (1, f64::INFINITY, 1000),
+            stagnation: (0.1 + 0.2, 20, 30),
+            termination: (123.456, 40),
+            operators: Some((vec![(0.5, 0.25, 3, 1e-7); 2], vec![(0.0, 1.0 / 3.0, 1, 0.0); 3])),
+        };
+
+        let file_name = std::env::temp_dir()
+            .join(format!("tsp-checkpoint-test-{}", std::process::id()));
+        let file_name = file_name.to_str().unwrap();
+        checkpoint.write(file_name).unwrap();
+        let mut read = Checkpoint::read(file_name).unwrap();
+
+        // A member whose tour repeats a city or leaves one out is rejected, as
+        // is a population out of order.
+        let contents = fs::read_to_string(file_name).unwrap();
+        let tour = checkpoint.population[3].0.data();
+        let mut repeated = tour.to_vec();
+        repeated[1] = repeated[0];
+        let mut dropped = tour.to_vec();
+        dropped.retain(|&city| city != 0);
+        for (fitness, tour) in [(1e7, repeated), (1e7, dropped), (0.0, tour.to_vec())] {
+            let cities = tour.iter().map(usize::to_string).collect::<Vec<String>>();
+            let line = format!("member {} {}\n", fitness, cities.join(" "));
+            fs::write(file_name, format!("{}{}", contents, line)).unwrap();
+            assert!(Checkpoint::read(file_name).is_err(), "{}", line);
+        }
+        fs::write(file_name, format!("{}schedule 0 1 12346\n", contents)).unwrap();
+        assert!(Checkpoint::read(file_name).is_err());
+        fs::remove_file(file_name).unwrap();
+
+        assert_eq!(checkpoint.evals, read.evals);
+        assert_eq!(checkpoint.elapsed, read.elapsed);
+        assert_eq!(checkpoint.lower_bound, read.lower_bound);
+        assert_eq!(checkpoint.best, read.best);
+        assert_eq!(checkpoint.population, read.population);
+        assert_eq!(checkpoint.schedule, read.schedule);
+        assert_eq!(checkpoint.stagnation, read.stagnation);
+        assert_eq!(checkpoint.termination, read.termination);
+        assert_eq!(checkpoint.operators, read.operators);
+        assert_eq!(rng.gen::<u64>(), read.rng.gen::<u64>());
+        assert_eq!(checkpoint.breeders, read.breeders);
+    }
+}
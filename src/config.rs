@@ -1,41 +1,450 @@
-use std::error::Error;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fs;
+use std::time::Duration;
+
+use crate::adaptive::Strategy;
+use crate::annealing::Cooling;
+use crate::ant_colony::AntSystem;
+use crate::checkpoint::Checkpoint;
+use crate::construction::{self, Heuristic};
+use crate::island::{Emigrants, Immigrants, Migration, Topology};
+use crate::iterated_local_search::Acceptance;
+use crate::local_search::{self, LocalSearch};
+use crate::population::PopulationModel;
+use crate::restart::Response;
+use crate::schedule::{Crossover, Mutation, Schedule};
+use crate::tabu::{Aspiration, Neighborhood};
+use crate::selection::{self, LinearRank, ParentSelection};
+use crate::termination::Termination;
+use crate::weighted_graph::WeightedGraph;
+
+/// The algorithm a run uses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Genetic,
+    Annealing,
+    AntColony,
+    Tabu,
+    IteratedLocalSearch,
+}
+
+impl Algorithm {
+    pub const ALL: [Self; 5] = [
+        Self::Genetic, Self::Annealing, Self::AntColony, Self::Tabu,
+        Self::IteratedLocalSearch,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name.trim())
+            .ok_or("unknown algorithm")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Genetic => "ga",
+            Self::Annealing => "annealing",
+            Self::AntColony => "aco",
+            Self::Tabu => "tabu",
+            Self::IteratedLocalSearch => "ils",
+        }
+    }
+}
 
 /// Options for configuring the evolutionary algorithm.
-/// Currently you can only configure population size and number of evaluations.
+/// The file name, population size and number of evaluations are given as
+/// positional arguments; everything else may be set with `--key value`
+/// options or read from a config file with `--config <file>`.
 pub struct Config {
     pub file_name: String,
     pub pop_size: usize,
     pub num_evals: usize,
+    pub schedule: Schedule,
+    /// If set, mutation and crossover operators are chosen adaptively
+    /// instead of by the schedule, whose survivor operators still apply.
+    pub operator_selection: Option<Strategy>,
+    pub parent_selection: Box<dyn ParentSelection>,
+    pub population_model: PopulationModel,
+    /// If set, children whose tour is already in the population are rejected.
+    pub reject_duplicates: bool,
+    /// Evaluations between lines of diversity statistics; 0 disables them.
+    pub diversity_interval: usize,
+    /// Evaluations without improvement before the population is considered
+    /// stagnant; 0 disables the check.
+    pub stagnation_window: usize,
+    /// Mean bond distance below which the population is considered
+    /// stagnant; 0 disables the check.
+    pub min_bond_distance: f64,
+    pub stagnation_response: Response,
+    /// Stop conditions checked in addition to the evaluation budget.
+    pub termination: Termination,
+    /// Fractions of the initial population built by construction heuristics.
+    pub seeding: Vec<(Heuristic, f64)>,
+    /// If set, the Held-Karp lower bound is computed before the run and used
+    /// as the termination lower bound.
+    pub compute_lower_bound: bool,
+    /// Seeds the random number generator, making runs reproducible.
+    pub seed: Option<u64>,
+    /// Threads across which the GA breeds each generation's offspring.
+    pub threads: usize,
+    /// Populations the GA evolves in parallel, each of pop_size.
+    pub islands: usize,
+    pub migration: Migration,
+    /// File the GA's state is saved to every `checkpoint_interval`
+    /// evaluations and when it stops.
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: usize,
+    /// State of an earlier GA run to continue from, whose evaluations count
+    /// towards num_evals.
+    pub resume: Option<Checkpoint>,
+    /// The algorithm to run. The options below apply only to the algorithms
+    /// other than the GA, apart from the local search and its candidates,
+    /// and those algorithms ignore the population options above.
+    pub algorithm: Algorithm,
+    pub cooling: Cooling,
+    /// Operators whose moves simulated annealing chooses between uniformly.
+    pub moves: Vec<Mutation>,
+    pub ant_system: AntSystem,
+    /// Length of each city's list of nearest neighbors that ants and local
+    /// searches restrict themselves to.
+    pub candidates: usize,
+    /// Local search applied to each child the GA breeds, each tour the ants
+    /// build, and after each kick of iterated local search, which uses 2-opt
+    /// with Or-opt if None.
+    pub local_search: Option<LocalSearch>,
+    /// Neighborhoods whose moves tabu search considers.
+    pub neighborhood: Vec<Neighborhood>,
+    /// Iterations for which tabu search may not add back an edge it removed.
+    pub tenure: usize,
+    pub aspiration: Aspiration,
+    pub acceptance: Acceptance,
 }
 
 impl Config {
-    pub fn from_args(mut args: impl Iterator<Item = String>) 
+    pub fn from_args(mut args: impl Iterator<Item = String>)
     -> Result<Self, &'static str> {
         let _ = args.next();
         let file_name = args.next()
-            .ok_or_else(|| "file name not provided")?;
+            .ok_or("file name not provided")?;
         let pop_size = args.next()
-            .ok_or_else(|| "pop size not provided")?
+            .ok_or("pop size not provided")?
             .parse::<usize>();
-        if let Err(_) = pop_size {
+        if pop_size.is_err() {
             return Err("could not parse pop size");
         }
 
         let num_evals = args.next()
-            .ok_or_else(|| "num evals not provided")?
+            .ok_or("num evals not provided")?
             .parse::<usize>();
-        if let Err(_) = num_evals {
+        if num_evals.is_err() {
             return Err("could not parse num evals");
         }
 
-        let pop_size = pop_size.unwrap();
-        let num_evals = num_evals.unwrap();
-        assert!(pop_size > 1);
-        assert!(num_evals > 0);
-        Ok(Self {
+        let mut config = Self {
             file_name,
-            pop_size,
-            num_evals,
-        })
+            pop_size: pop_size.unwrap(),
+            num_evals: num_evals.unwrap(),
+            schedule: Schedule::default(),
+            operator_selection: None,
+            parent_selection: Box::new(LinearRank { s: 2.0 }),
+            population_model: PopulationModel::SteadyState,
+            reject_duplicates: false,
+            diversity_interval: 1000,
+            stagnation_window: 0,
+            min_bond_distance: 0.0,
+            stagnation_response: Response::Restart,
+            termination: Termination::default(),
+            seeding: Vec::new(),
+            compute_lower_bound: false,
+            seed: None,
+            threads: 1,
+            islands: 1,
+            migration: Migration::default(),
+            checkpoint: None,
+            checkpoint_interval: 10000,
+            resume: None,
+            algorithm: Algorithm::Genetic,
+            cooling: Cooling::default(),
+            moves: Mutation::ALL.to_vec(),
+            ant_system: AntSystem::Acs,
+            candidates: local_search::DEFAULT_CANDIDATES,
+            local_search: None,
+            neighborhood: vec![Neighborhood::TwoOpt],
+            tenure: 10,
+            aspiration: Aspiration::Best,
+            acceptance: Acceptance::Better,
+        };
+
+        while let Some(flag) = args.next() {
+            let key = flag.strip_prefix("--")
+                .ok_or("options must be of the form --key value")?;
+            let value = args.next()
+                .ok_or("option provided without a value")?;
+            config.set(key, &value)?;
+        }
+
+        if config.pop_size < 2 {
+            return Err("pop size must be at least 2");
+        }
+        if config.num_evals == 0 {
+            return Err("num evals must be positive");
+        }
+        config.population_model.validate(config.pop_size)?;
+        if config.termination.max_gap.is_some()
+            && config.termination.lower_bound.is_none()
+            && !config.compute_lower_bound {
+            return Err("max gap requires a lower bound");
+        }
+        if config.islands > 1 && config.algorithm != Algorithm::Genetic {
+            return Err("islands require the genetic algorithm");
+        }
+        if config.islands > 1 && config.migration.size >= config.pop_size {
+            return Err("migration size must be less than the pop size");
+        }
+        if (config.checkpoint.is_some() || config.resume.is_some())
+            && (config.algorithm != Algorithm::Genetic || config.islands > 1) {
+            return Err("checkpoints require the genetic algorithm on a single island");
+        }
+        if let Some(checkpoint) = &config.resume {
+            if checkpoint.population.len() != config.pop_size {
+                return Err("checkpoint does not match the pop size");
+            }
+            if checkpoint.breeders.len() != config.threads {
+                return Err("checkpoint does not match the number of threads");
+            }
+            if checkpoint.schedule.0 >= config.schedule.phases().len() {
+                return Err("checkpoint does not match the schedule");
+            }
+            let operators_match = match (&checkpoint.operators, config.operator_selection) {
+                (None, None) => true,
+                (Some((crossovers, mutations)), Some(_)) =>
+                    crossovers.len() == Crossover::ALL.len()
+                        && mutations.len() == Mutation::ALL.len(),
+                _ => false,
+            };
+            if !operators_match {
+                return Err("checkpoint does not match the operator selection");
+            }
+        }
+        Ok(config)
+    }
+
+    /// Sets the option named by key, as given on the command line or in a
+    /// config file. `config` loads the named file, one `key = value` pair per
+    /// line, with blank lines and lines starting with `#` ignored.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), &'static str> {
+        let value = value.trim();
+        match key.trim() {
+            "config" => self.load_file(value)?,
+            "pop_size" => {
+                self.pop_size = value.parse::<usize>()
+                    .map_err(|_| "could not parse pop size")?;
+            }
+            "num_evals" => {
+                self.num_evals = value.parse::<usize>()
+                    .map_err(|_| "could not parse num evals")?;
+            }
+            "schedule" => self.schedule = Schedule::parse(value)?,
+            "operator_selection" => {
+                self.operator_selection = match value {
+                    "schedule" => None,
+                    _ => Some(Strategy::parse(value)?),
+                };
+            }
+            "parent_selection" => {
+                self.parent_selection = selection::parse(value)?;
+            }
+            "population_model" => {
+                self.population_model = PopulationModel::parse(value)?;
+            }
+            "reject_duplicates" => {
+                self.reject_duplicates = value.parse::<bool>()
+                    .map_err(|_| "reject duplicates must be true or false")?;
+            }
+            "diversity_interval" => {
+                self.diversity_interval = value.parse::<usize>()
+                    .map_err(|_| "could not parse diversity interval")?;
+            }
+            "stagnation_window" => {
+                self.stagnation_window = value.parse::<usize>()
+                    .map_err(|_| "could not parse stagnation window")?;
+            }
+            "min_bond_distance" => {
+                self.min_bond_distance = value.parse::<f64>()
+                    .map_err(|_| "could not parse min bond distance")?;
+            }
+            "stagnation_response" => {
+                self.stagnation_response = Response::parse(value)?;
+            }
+            "time_limit" => {
+                let seconds = value.parse::<f64>()
+                    .map_err(|_| "could not parse time limit")?;
+                let limit = Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| "time limit must be a non-negative number of seconds")?;
+                self.termination.time_limit = Some(limit);
+            }
+            "target_length" => {
+                self.termination.target_length = Some(value.parse::<f64>()
+                    .map_err(|_| "could not parse target length")?);
+            }
+            "lower_bound" if value == "held-karp" => {
+                self.compute_lower_bound = true;
+                self.termination.lower_bound = None;
+            }
+            "lower_bound" => {
+                self.compute_lower_bound = false;
+                self.termination.lower_bound = Some(value.parse::<f64>()
+                    .map_err(|_| "could not parse lower bound")?);
+            }
+            "max_gap" => {
+                self.termination.max_gap = Some(value.parse::<f64>()
+                    .map_err(|_| "could not parse max gap")?);
+            }
+            "max_stall" => {
+                self.termination.max_stall = Some(value.parse::<usize>()
+                    .map_err(|_| "could not parse max stall")?);
+            }
+            "seeding" => self.seeding = construction::parse_seeding(value)?,
+            "seed" => {
+                self.seed = Some(value.parse::<u64>()
+                    .map_err(|_| "could not parse seed")?);
+            }
+            "threads" => {
+                self.threads = value.parse::<usize>()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .ok_or("threads must be a positive integer")?;
+            }
+            "islands" => {
+                self.islands = value.parse::<usize>()
+                    .ok()
+                    .filter(|&islands| islands > 0)
+                    .ok_or("islands must be a positive integer")?;
+            }
+            "topology" => self.migration.topology = Topology::parse(value)?,
+            "migration_interval" => {
+                self.migration.interval = value.parse::<usize>()
+                    .ok()
+                    .filter(|&interval| interval > 0)
+                    .ok_or("migration interval must be a positive integer")?;
+            }
+            "migration_size" => {
+                self.migration.size = value.parse::<usize>()
+                    .map_err(|_| "could not parse migration size")?;
+            }
+            "checkpoint" => self.checkpoint = Some(value.to_string()),
+            "checkpoint_interval" => {
+                self.checkpoint_interval = value.parse::<usize>()
+                    .ok()
+                    .filter(|&interval| interval > 0)
+                    .ok_or("checkpoint interval must be a positive integer")?;
+            }
+            "resume" => self.resume = Some(Checkpoint::read(value)?),
+            "emigrants" => self.migration.emigrants = Emigrants::parse(value)?,
+            "immigrants" => self.migration.immigrants = Immigrants::parse(value)?,
+            "algorithm" => self.algorithm = Algorithm::parse(value)?,
+            "cooling" => self.cooling = Cooling::parse(value)?,
+            "moves" => {
+                self.moves = value
+                    .split(',')
+                    .map(Mutation::parse)
+                    .collect::<Result<Vec<Mutation>, &'static str>>()?;
+            }
+            "ant_system" => self.ant_system = AntSystem::parse(value)?,
+            "candidates" => {
+                self.candidates = value.parse::<usize>()
+                    .ok()
+                    .filter(|&candidates| candidates > 0)
+                    .ok_or("candidates must be a positive integer")?;
+            }
+            "neighborhood" => {
+                self.neighborhood = value
+                    .split(',')
+                    .map(Neighborhood::parse)
+                    .collect::<Result<Vec<Neighborhood>, &'static str>>()?;
+            }
+            "tenure" => {
+                self.tenure = value.parse::<usize>()
+                    .map_err(|_| "could not parse tenure")?;
+            }
+            "aspiration" => self.aspiration = Aspiration::parse(value)?,
+            "acceptance" => self.acceptance = Acceptance::parse(value)?,
+            "local_search" => {
+                self.local_search = match value {
+                    "none" => None,
+                    _ => Some(LocalSearch::parse(value)?),
+                };
+            }
+            _ => return Err("unknown option"),
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the config cannot be run on the graph, which is
+    /// the case if it resumes from a checkpoint with tours of another length.
+    pub fn validate_instance(&self, graph: &WeightedGraph) -> Result<(), &'static str> {
+        let num_cities = graph.num_vertices();
+        if let Some(checkpoint) = &self.resume {
+            let mut members = std::iter::once(&checkpoint.best).chain(&checkpoint.population);
+            if members.any(|(genotype, _)| genotype.data().len() != num_cities) {
+                return Err("checkpoint does not match the instance");
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a random number generator seeded with `seed`, or by the
+    /// operating system if there is none.
+    pub fn rng(&self) -> ChaCha8Rng {
+        match self.seed {
+            Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+            None => ChaCha8Rng::from_entropy(),
+        }
+    }
+
+    fn load_file(&mut self, file_name: &str) -> Result<(), &'static str> {
+        let contents = fs::read_to_string(file_name)
+            .map_err(|_| "error trying to read config file")?;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or("config file lines must be of the form key = value")?;
+            if key.trim() == "config" {
+                return Err("config files may not include other config files");
+            }
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn options_are_parsed_after_positional_arguments() {
+        let config = Config::from_args(
+            args("tsp data.txt 50 2000 --schedule inversion,edge,worst")
+        ).unwrap();
+
+        assert_eq!("data.txt", config.file_name);
+        assert_eq!(50, config.pop_size);
+        assert_eq!(2000, config.num_evals);
+        assert_eq!(1, config.schedule.phases().len());
+
+        assert!(Config::from_args(args("tsp data.txt 50 2000 --bogus 1")).is_err());
+        assert!(Config::from_args(args("tsp data.txt 50 2000 --schedule")).is_err());
+        assert!(Config::from_args(args("tsp data.txt 1 2000")).is_err());
+        assert!(Config::from_args(args("tsp data.txt 50 0")).is_err());
     }
 }
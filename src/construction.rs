@@ -0,0 +1,548 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::genotype::Genotype;
+use crate::weighted_graph::WeightedGraph;
+
+/// Tour construction heuristics, usable on their own or to seed the initial
+/// population of the genetic algorithm.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Heuristic {
+    NearestNeighbor,
+    GreedyEdge,
+    CheapestInsertion,
+    FarthestInsertion,
+    SpaceFillingCurve,
+    Christofides,
+    ChristofidesExact,
+}
+
+impl Heuristic {
+    pub const ALL: [Self; 7] = [
+        Self::NearestNeighbor,
+        Self::GreedyEdge,
+        Self::CheapestInsertion,
+        Self::FarthestInsertion,
+        Self::SpaceFillingCurve,
+        Self::Christofides,
+        Self::ChristofidesExact,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|heuristic| heuristic.name() == name.trim())
+            .ok_or("unknown construction heuristic")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NearestNeighbor => "nn",
+            Self::GreedyEdge => "greedy",
+            Self::CheapestInsertion => "cheapest",
+            Self::FarthestInsertion => "farthest",
+            Self::SpaceFillingCurve => "hilbert",
+            Self::Christofides => "christofides",
+            Self::ChristofidesExact => "christofides-exact",
+        }
+    }
+
+    /// Returns true if the heuristic always builds the same tour.
+    pub fn is_deterministic(self) -> bool {
+        matches!(
+            self,
+            Self::GreedyEdge | Self::SpaceFillingCurve
+                | Self::Christofides | Self::ChristofidesExact
+        )
+    }
+
+    /// Builds a tour, starting from a random city where the heuristic needs
+    /// a starting point.
+    pub fn construct(self, graph: &WeightedGraph, rng: &mut ChaCha8Rng) -> Genotype {
+        let start = rng.gen_range(0..graph.num_vertices());
+        let tour = match self {
+            Self::NearestNeighbor => nearest_neighbor(graph, start),
+            Self::GreedyEdge => greedy_edge(graph),
+            Self::CheapestInsertion => cheapest_insertion(graph, start),
+            Self::FarthestInsertion => farthest_insertion(graph, start),
+            Self::SpaceFillingCurve => space_filling_curve(graph.points()),
+            Self::Christofides => christofides(graph, Matching::Greedy),
+            Self::ChristofidesExact => christofides(graph, Matching::Exact),
+        };
+        Genotype::from_data(tour)
+    }
+}
+
+/// Parses a list of heuristics with the fraction of the initial population
+/// each should build, such as `nn:0.2,greedy:0.05`.
+pub fn parse_seeding(spec: &str) -> Result<Vec<(Heuristic, f64)>, &'static str> {
+    let seeding = spec
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (name, fraction) = entry.split_once(':')
+                .ok_or("seeding entries must be of the form heuristic:fraction")?;
+            let fraction = fraction.trim().parse::<f64>()
+                .map_err(|_| "could not parse seeding fraction")?;
+            Ok((Heuristic::parse(name)?, fraction))
+        })
+        .collect::<Result<Vec<(Heuristic, f64)>, &'static str>>()?;
+
+    let total = seeding.iter().map(|(_, fraction)| fraction).sum::<f64>();
+    if seeding.iter().any(|&(_, fraction)| fraction < 0.0) || total > 1.0 {
+        return Err("seeding fractions must be non-negative and sum to at most 1");
+    }
+    Ok(seeding)
+}
+
+/// Repeatedly moves to the closest city not yet visited.
+pub fn nearest_neighbor(graph: &WeightedGraph, start: usize) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    let mut visited = vec![false; num_vertices];
+    let mut tour = Vec::with_capacity(num_vertices);
+
+    let mut current = start;
+    visited[current] = true;
+    tour.push(current);
+    while tour.len() < num_vertices {
+        let next = (0..num_vertices)
+            .filter(|&v| !visited[v])
+            .min_by(|&v, &u| {
+                graph.weight_between(current, v)
+                    .partial_cmp(&graph.weight_between(current, u))
+                    .unwrap()
+            })
+            .unwrap();
+        visited[next] = true;
+        tour.push(next);
+        current = next;
+    }
+    tour
+}
+
+/// Adds edges in order of increasing weight, skipping any that would give a
+/// city three edges or close a cycle early, until they form a tour.
+pub fn greedy_edge(graph: &WeightedGraph) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    if num_vertices <= 3 {
+        return (0..num_vertices).collect();
+    }
+
+    let mut edges = Vec::with_capacity(num_vertices * (num_vertices - 1) / 2);
+    for v in 0..num_vertices {
+        for u in v+1..num_vertices {
+            edges.push((graph.weight_between(v, u), v, u));
+        }
+    }
+    edges.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    let mut fragments = DisjointSets::new(num_vertices);
+    let mut adjacent = vec![Vec::with_capacity(2); num_vertices];
+    let mut num_edges = 0;
+    for (_, v, u) in edges {
+        if num_edges == num_vertices - 1 {
+            break;
+        }
+        if adjacent[v].len() < 2 && adjacent[u].len() < 2
+            && fragments.union(v, u) {
+            adjacent[v].push(u);
+            adjacent[u].push(v);
+            num_edges += 1;
+        }
+    }
+
+    // The edges now form a single path; walk it from one end.
+    let mut tour = Vec::with_capacity(num_vertices);
+    let mut prev = usize::MAX;
+    let mut current = (0..num_vertices)
+        .find(|&v| adjacent[v].len() < 2)
+        .unwrap();
+    loop {
+        tour.push(current);
+        match adjacent[current].iter().find(|&&v| v != prev) {
+            None => break,
+            Some(&next) => {
+                prev = current;
+                current = next;
+            }
+        }
+    }
+    tour
+}
+
+/// Starting from a single city, repeatedly inserts whichever remaining city
+/// increases the tour length the least, at the position where it does so.
+pub fn cheapest_insertion(graph: &WeightedGraph, start: usize) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    // The tour is kept as a linked list of successors.
+    let mut next = vec![usize::MAX; num_vertices];
+    next[start] = start;
+
+    let insertion_cost = |next: &[usize], a: usize, c: usize| {
+        let b = next[a];
+        graph.weight_between(a, c) + graph.weight_between(c, b)
+            - graph.weight_between(a, b)
+    };
+
+    // For each city outside the tour, the cheapest cost of inserting it and
+    // the city after which to do so.
+    let mut best = (0..num_vertices)
+        .map(|c| (insertion_cost(&next, start, c), start))
+        .collect::<Vec<(f64, usize)>>();
+
+    for _ in 1..num_vertices {
+        let c = (0..num_vertices)
+            .filter(|&c| next[c] == usize::MAX)
+            .min_by(|&x, &y| best[x].0.partial_cmp(&best[y].0).unwrap())
+            .unwrap();
+        let a = best[c].1;
+        next[c] = next[a];
+        next[a] = c;
+
+        for x in 0..num_vertices {
+            if next[x] != usize::MAX {
+                continue;
+            }
+            if best[x].1 == a {
+                // The edge it would have been inserted into is gone.
+                best[x] = tour_vertices(&next, start)
+                    .map(|v| (insertion_cost(&next, v, x), v))
+                    .min_by(|p, q| p.0.partial_cmp(&q.0).unwrap())
+                    .unwrap();
+            } else {
+                for v in [a, c] {
+                    let cost = insertion_cost(&next, v, x);
+                    if cost < best[x].0 {
+                        best[x] = (cost, v);
+                    }
+                }
+            }
+        }
+    }
+    tour_vertices(&next, start).collect()
+}
+
+/// Starting from a single city, repeatedly takes whichever remaining city is
+/// farthest from the tour and inserts it where it increases the length least.
+pub fn farthest_insertion(graph: &WeightedGraph, start: usize) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    let mut next = vec![usize::MAX; num_vertices];
+    next[start] = start;
+
+    let mut distance_to_tour = (0..num_vertices)
+        .map(|v| graph.weight_between(start, v))
+        .collect::<Vec<f64>>();
+
+    for _ in 1..num_vertices {
+        let c = (0..num_vertices)
+            .filter(|&c| next[c] == usize::MAX)
+            .max_by(|&x, &y| {
+                distance_to_tour[x].partial_cmp(&distance_to_tour[y]).unwrap()
+            })
+            .unwrap();
+
+        let a = tour_vertices(&next, start)
+            .min_by(|&x, &y| {
+                let cost = |a: usize| graph.weight_between(a, c)
+                    + graph.weight_between(c, next[a])
+                    - graph.weight_between(a, next[a]);
+                cost(x).partial_cmp(&cost(y)).unwrap()
+            })
+            .unwrap();
+        next[c] = next[a];
+        next[a] = c;
+
+        for (v, distance) in distance_to_tour.iter_mut().enumerate() {
+            *distance = distance.min(graph.weight_between(c, v));
+        }
+    }
+    tour_vertices(&next, start).collect()
+}
+
+/// Visits the cities in the order they appear along a Hilbert curve covering
+/// their bounding box, so that cities close on the curve are close together.
+pub fn space_filling_curve(points: &[(f64, f64)]) -> Vec<usize> {
+    const SIDE: u64 = 1 << 16;
+
+    let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    let extent = (max_x - min_x).max(max_y - min_y).max(f64::MIN_POSITIVE);
+
+    let scale = |value: f64, min: f64| {
+        (((value - min) / extent) * (SIDE - 1) as f64).round() as u64
+    };
+    let mut tour = (0..points.len()).collect::<Vec<usize>>();
+    tour.sort_by_key(|&v| {
+        hilbert_index(SIDE, scale(points[v].0, min_x), scale(points[v].1, min_y))
+    });
+    tour
+}
+
+/// How Christofides' algorithm pairs up the odd degree vertices of the
+/// minimum spanning tree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Matching {
+    /// Repeatedly pairs the two closest unmatched vertices.
+    Greedy,
+    /// A minimum-weight perfect matching, found by dynamic programming over
+    /// subsets. Only used for up to MAX_EXACT_MATCHING odd vertices; beyond
+    /// that the greedy matching is used instead.
+    Exact,
+}
+
+pub const MAX_EXACT_MATCHING: usize = 20;
+
+/// Christofides' algorithm: joins a minimum spanning tree with a matching on
+/// its odd degree vertices, then shortcuts an Euler tour of the result. With
+/// an exact matching the tour is at most 1.5 times the optimal length.
+pub fn christofides(graph: &WeightedGraph, matching: Matching) -> Vec<usize> {
+    let num_vertices = graph.num_vertices();
+    if num_vertices <= 3 {
+        return (0..num_vertices).collect();
+    }
+
+    let mut adjacent = vec![Vec::new(); num_vertices];
+    for (v, u) in graph.minimum_spanning_tree() {
+        adjacent[v].push(u);
+        adjacent[u].push(v);
+    }
+
+    let odd = (0..num_vertices)
+        .filter(|&v| adjacent[v].len() % 2 == 1)
+        .collect::<Vec<usize>>();
+    let pairs = match matching {
+        Matching::Exact if odd.len() <= MAX_EXACT_MATCHING =>
+            exact_matching(graph, &odd),
+        _ => greedy_matching(graph, &odd),
+    };
+    for (v, u) in pairs {
+        adjacent[v].push(u);
+        adjacent[u].push(v);
+    }
+
+    let mut visited = vec![false; num_vertices];
+    euler_tour(adjacent)
+        .into_iter()
+        .filter(|&v| !std::mem::replace(&mut visited[v], true))
+        .collect()
+}
+
+fn greedy_matching(graph: &WeightedGraph, vertices: &[usize]) -> Vec<(usize, usize)> {
+    let mut candidates = Vec::new();
+    for (i, &v) in vertices.iter().enumerate() {
+        for &u in &vertices[i+1..] {
+            candidates.push((graph.weight_between(v, u), v, u));
+        }
+    }
+    candidates.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    let mut matched = vec![false; graph.num_vertices()];
+    let mut pairs = Vec::with_capacity(vertices.len() / 2);
+    for (_, v, u) in candidates {
+        if !matched[v] && !matched[u] {
+            matched[v] = true;
+            matched[u] = true;
+            pairs.push((v, u));
+        }
+    }
+    pairs
+}
+
+/// Finds a minimum-weight perfect matching on an even number of vertices.
+/// Each subset of matched vertices is extended by matching its lowest
+/// unmatched vertex, so there are 2^k states for k vertices.
+fn exact_matching(graph: &WeightedGraph, vertices: &[usize]) -> Vec<(usize, usize)> {
+    let k = vertices.len();
+    let full = (1usize << k) - 1;
+    let mut cost = vec![f64::INFINITY; full + 1];
+    let mut choice = vec![(0, 0); full + 1];
+    cost[0] = 0.0;
+
+    for mask in 0..full {
+        if cost[mask].is_infinite() {
+            continue;
+        }
+        let i = (!mask).trailing_zeros() as usize;
+        for j in i+1..k {
+            if mask & (1 << j) != 0 {
+                continue;
+            }
+            let next = mask | (1 << i) | (1 << j);
+            let next_cost = cost[mask] + graph.weight_between(vertices[i], vertices[j]);
+            if next_cost < cost[next] {
+                cost[next] = next_cost;
+                choice[next] = (i, j);
+            }
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(k / 2);
+    let mut mask = full;
+    while mask != 0 {
+        let (i, j) = choice[mask];
+        pairs.push((vertices[i], vertices[j]));
+        mask &= !((1 << i) | (1 << j));
+    }
+    pairs
+}
+
+/// Returns an Euler circuit of a connected multigraph whose vertices all
+/// have even degree, by Hierholzer's algorithm.
+fn euler_tour(mut adjacent: Vec<Vec<usize>>) -> Vec<usize> {
+    let mut circuit = Vec::new();
+    let mut stack = vec![0];
+    while let Some(&v) = stack.last() {
+        match adjacent[v].pop() {
+            None => {
+                circuit.push(v);
+                stack.pop();
+            }
+            Some(u) => {
+                // Remove the matching copy of the edge from u's list.
+                let idx = adjacent[u].iter().position(|&x| x == v).unwrap();
+                adjacent[u].swap_remove(idx);
+                stack.push(u);
+            }
+        }
+    }
+    circuit
+}
+
+/// Returns the distance along the Hilbert curve filling a side by side grid
+/// of the cell at (x, y).
+fn hilbert_index(side: u64, mut x: u64, mut y: u64) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        index += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Iterates over a tour stored as a linked list of successors.
+fn tour_vertices(next: &[usize], start: usize) -> impl Iterator<Item = usize> + '_ {
+    let mut current = Some(start);
+    std::iter::from_fn(move || {
+        let v = current?;
+        current = Some(next[v]).filter(|&u| u != start);
+        Some(v)
+    })
+}
+
+/// Union-find over vertices, used to keep greedy fragments acyclic.
+struct DisjointSets {
+    parent: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(size: usize) -> Self {
+        Self { parent: (0..size).collect() }
+    }
+
+    fn find(&mut self, v: usize) -> usize {
+        let mut root = v;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut v = v;
+        while self.parent[v] != root {
+            let next = self.parent[v];
+            self.parent[v] = root;
+            v = next;
+        }
+        root
+    }
+
+    /// Joins the sets containing v and u. Returns false if they were
+    /// already the same set.
+    fn union(&mut self, v: usize, u: usize) -> bool {
+        let (root_v, root_u) = (self.find(v), self.find(u));
+        if root_v == root_u {
+            return false;
+        }
+        self.parent[root_v] = root_u;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::weighted_graph;
+
+    /// Cities on a circle, visited in order by the optimal tour.
+    fn circle(num_points: usize) -> WeightedGraph {
+        let points = (0..num_points)
+            .map(|i| {
+                let angle = 2.0 * std::f64::consts::PI * i as f64 / num_points as f64;
+                (angle.cos() * 100.0, angle.sin() * 100.0)
+            })
+            .collect::<Vec<(f64, f64)>>();
+        WeightedGraph::from_points(points)
+    }
+
+    #[test]
+    fn heuristics_find_the_circle_tour() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let graph = circle(20);
+        let optimum = crate::fitness(&graph, &(0..20).collect::<Vec<usize>>());
+
+        for heuristic in Heuristic::ALL {
+            if heuristic == Heuristic::SpaceFillingCurve {
+                continue;
+            }
+            let tour = heuristic.construct(&graph, &mut rng);
+            let length = crate::fitness(&graph, tour.data());
+            assert!(
+                (length - optimum).abs() < 1e-6,
+                "{} built a tour of length {} instead of {}",
+                heuristic.name(), length, optimum
+            );
+        }
+    }
+
+    #[test]
+    fn space_filling_curve_follows_the_grid() {
+        let points = (0..16)
+            .map(|i| ((i % 4) as f64, (i / 4) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points.clone());
+        let tour = space_filling_curve(&points);
+
+        // 15 unit steps along the curve, then 3 back from its far corner.
+        assert_eq!(18.0, crate::fitness(&graph, &tour));
+    }
+
+    #[test]
+    fn christofides_is_within_its_approximation_ratio() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(8, 1));
+        let (_, optimum) = crate::exact::dynamic_programming(&graph).unwrap();
+
+        let tour = christofides(&graph, Matching::Exact);
+        assert_eq!(8, Genotype::from_data(tour.clone()).num_alleles());
+        assert!(crate::fitness(&graph, &tour) <= 1.5 * optimum + 1e-9);
+    }
+
+    #[test]
+    fn seeding_is_parsed_correctly() {
+        let seeding = parse_seeding("nn:0.5, hilbert:0.25").unwrap();
+        assert_eq!(
+            vec![(Heuristic::NearestNeighbor, 0.5), (Heuristic::SpaceFillingCurve, 0.25)],
+            seeding
+        );
+        assert!(parse_seeding("nn:0.8,greedy:0.3").is_err());
+        assert!(parse_seeding("nn").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::genotype::Genotype;
+
+/// Measures of how spread out a population is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Diversity {
+    /// Mean number of edges by which a pair of individuals differ.
+    pub mean_bond_distance: f64,
+    /// Shannon entropy of the population-wide edge frequency table.
+    pub edge_entropy: f64,
+    /// Number of distinct tours, ignoring rotation and reflection.
+    pub unique_tours: usize,
+}
+
+impl Diversity {
+    pub fn measure(pop_and_fitness: &[(Genotype, f64)]) -> Self {
+        let pop_size = pop_and_fitness.len();
+
+        let mut total_distance = 0;
+        for i in 0..pop_size {
+            for j in i+1..pop_size {
+                total_distance += pop_and_fitness[i].0
+                    .bond_distance(&pop_and_fitness[j].0);
+            }
+        }
+        let num_pairs = pop_size * (pop_size - 1) / 2;
+        let mean_bond_distance = if num_pairs == 0 {
+            0.0
+        } else {
+            total_distance as f64 / num_pairs as f64
+        };
+
+        let unique_tours = pop_and_fitness
+            .iter()
+            .map(|(genotype, _)| genotype.canonical())
+            .collect::<HashSet<Vec<usize>>>()
+            .len();
+
+        Self {
+            mean_bond_distance,
+            edge_entropy: EdgeFrequencies::from_population(pop_and_fitness).entropy(),
+            unique_tours,
+        }
+    }
+}
+
+/// Counts how many tours in a population use each undirected edge.
+pub struct EdgeFrequencies {
+    counts: BTreeMap<(usize, usize), usize>,
+    total: usize,
+}
+
+impl EdgeFrequencies {
+    pub fn from_population(pop_and_fitness: &[(Genotype, f64)]) -> Self {
+        let mut frequencies = Self {
+            counts: BTreeMap::new(),
+            total: 0,
+        };
+        for (genotype, _) in pop_and_fitness {
+            frequencies.add(genotype);
+        }
+        frequencies
+    }
+
+    pub fn add(&mut self, genotype: &Genotype) {
+        for edge in edges(genotype) {
+            *self.counts.entry(edge).or_insert(0) += 1;
+            self.total += 1;
+        }
+    }
+
+    /// Removes a tour previously added. Panics if it was not added.
+    pub fn remove(&mut self, genotype: &Genotype) {
+        for edge in edges(genotype) {
+            let count = self.counts.get_mut(&edge)
+                .expect("removed an edge that was never added");
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&edge);
+            }
+            self.total -= 1;
+        }
+    }
+
+    /// Returns the Shannon entropy of the edge distribution, which is higher
+    /// the more evenly the population spreads across distinct edges.
+    pub fn entropy(&self) -> f64 {
+        let total = self.total as f64;
+        self.counts
+            .values()
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.ln()
+            })
+            .sum()
+    }
+
+    /// Returns the entropy the table would have if the tour `out` were
+    /// replaced by the tour `into`, without modifying the table.
+    pub fn entropy_after_replacing(&self, out: &Genotype, into: &Genotype) -> f64 {
+        let mut changes: BTreeMap<(usize, usize), isize> = BTreeMap::new();
+        for edge in edges(out) {
+            *changes.entry(edge).or_insert(0) -= 1;
+        }
+        for edge in edges(into) {
+            *changes.entry(edge).or_insert(0) += 1;
+        }
+
+        // Both tours have the same number of edges, so the total is unchanged.
+        let total = self.total as f64;
+        let term = |count: usize| {
+            if count == 0 {
+                0.0
+            } else {
+                let p = count as f64 / total;
+                -p * p.ln()
+            }
+        };
+        let mut entropy = self.entropy();
+        for (edge, change) in changes {
+            let count = self.counts.get(&edge).copied().unwrap_or(0);
+            entropy += term((count as isize + change) as usize) - term(count);
+        }
+        entropy
+    }
+}
+
+/// Returns the edges of a tour, each with its smaller endpoint first.
+fn edges(genotype: &Genotype) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let data = genotype.data();
+    let num_alleles = data.len();
+    (0..num_alleles).map(move |i| {
+        let (v, u) = (data[i], data[(i + 1) % num_alleles]);
+        (v.min(u), v.max(u))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn clones_have_no_diversity() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let tour = Genotype::random(6, &mut rng);
+        let reflected = tour.data().iter().rev().copied().collect::<Vec<usize>>();
+        let pop = vec![
+            (Genotype::from_data(tour.data().to_vec()), 0.0),
+            (Genotype::from_data(reflected), 0.0),
+        ];
+
+        let diversity = Diversity::measure(&pop);
+        assert_eq!(0.0, diversity.mean_bond_distance);
+        assert_eq!(1, diversity.unique_tours);
+        // Six edges, each used twice, gives entropy ln 6.
+        assert!((diversity.edge_entropy - 6.0_f64.ln()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn entropy_tracks_replacements() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let pop = (0..5)
+            .map(|_| (Genotype::random(8, &mut rng), 0.0))
+            .collect::<Vec<(Genotype, f64)>>();
+        let child = Genotype::random(8, &mut rng);
+
+        let mut frequencies = EdgeFrequencies::from_population(&pop);
+        let predicted = frequencies.entropy_after_replacing(&pop[2].0, &child);
+        frequencies.remove(&pop[2].0);
+        frequencies.add(&child);
+
+        assert!((predicted - frequencies.entropy()).abs() < 1e-9);
+    }
+}
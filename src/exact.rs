@@ -0,0 +1,460 @@
+use std::time::{Duration, Instant};
+
+use crate::bound::{HeldKarp, OneTree};
+use crate::observer::{Event, Observer, Signal};
+use crate::weighted_graph::WeightedGraph;
+
+/// The largest instance the dynamic programming solver accepts. Its table
+/// has 2^(n-1) * (n-1) entries, about 100 MB at this size.
+pub const MAX_DYNAMIC_PROGRAMMING_CITIES: usize = 20;
+
+/// Finds a provably optimal tour with the Held-Karp dynamic program: for
+/// every subset S of cities other than city 0 and every city j in S, the
+/// shortest path that starts at city 0, visits exactly S and ends at j.
+/// Returns the tour and its length, or an Error if the instance has more
+/// than MAX_DYNAMIC_PROGRAMMING_CITIES cities.
+pub fn dynamic_programming(graph: &WeightedGraph)
+-> Result<(Vec<usize>, f64), &'static str> {
+    let num_vertices = graph.num_vertices();
+    if num_vertices > MAX_DYNAMIC_PROGRAMMING_CITIES {
+        return Err("too many cities for the dynamic programming solver");
+    }
+    if num_vertices <= 3 {
+        let tour = (0..num_vertices).collect::<Vec<usize>>();
+        let length = match num_vertices {
+            0 => 0.0,
+            _ => crate::fitness(graph, &tour),
+        };
+        return Ok((tour, length));
+    }
+
+    // City i + 1 is bit i of a subset, since city 0 is always the start.
+    let k = num_vertices - 1;
+    let num_subsets = 1usize << k;
+    let idx = |subset: usize, last: usize| subset * k + last;
+    let mut length = vec![f64::INFINITY; num_subsets * k];
+    let mut previous = vec![u8::MAX; num_subsets * k];
+
+    for last in 0..k {
+        length[idx(1 << last, last)] = graph.weight_between(0, last + 1);
+    }
+    for subset in 1..num_subsets {
+        for last in 0..k {
+            let current = length[idx(subset, last)];
+            if subset & (1 << last) == 0 || current.is_infinite() {
+                continue;
+            }
+            for next in 0..k {
+                if subset & (1 << next) != 0 {
+                    continue;
+                }
+                let extended = idx(subset | (1 << next), next);
+                let candidate = current + graph.weight_between(last + 1, next + 1);
+                if candidate < length[extended] {
+                    length[extended] = candidate;
+                    previous[extended] = last as u8;
+                }
+            }
+        }
+    }
+
+    let full = num_subsets - 1;
+    let (mut last, best) = (0..k)
+        .map(|last| (last, length[idx(full, last)] + graph.weight_between(last + 1, 0)))
+        .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap())
+        .unwrap();
+
+    let mut tour = Vec::with_capacity(num_vertices);
+    let mut subset = full;
+    while subset != 0 {
+        tour.push(last + 1);
+        let prev = previous[idx(subset, last)];
+        subset &= !(1 << last);
+        last = prev as usize;
+    }
+    tour.push(0);
+    tour.reverse();
+
+    Ok((tour, best))
+}
+
+/// Iterations of subgradient ascent used for the bound at the root of the
+/// search tree and at every other node.
+const ROOT_ITERATIONS: usize = 1000;
+const NODE_ITERATIONS: usize = 50;
+
+/// The outcome of a branch-and-bound search.
+#[derive(Clone, Debug)]
+pub struct BranchAndBoundResult {
+    /// The best tour found, as a sequence of city indices.
+    pub best_tour: Vec<usize>,
+    pub best_length: f64,
+    /// A lower bound on the optimal tour length; equal to best_length if the
+    /// search finished.
+    pub lower_bound: f64,
+    /// True if the search finished, proving best_tour optimal.
+    pub optimal: bool,
+    /// Number of nodes of the search tree whose bound was computed.
+    pub nodes: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Constraint {
+    Free,
+    Forced,
+    Forbidden,
+}
+
+/// A node of the search tree: the edges forced into or forbidden from the
+/// tour, along with the penalties and bound inherited from its parent.
+struct Node {
+    decisions: Vec<((usize, usize), Constraint)>,
+    penalties: Vec<f64>,
+    bound: f64,
+}
+
+/// What computing the bound at a node found.
+enum Outcome {
+    /// No tour satisfies the node's constraints.
+    Infeasible,
+    /// The 1-tree is a tour, so is optimal under the node's constraints.
+    Tour(Vec<usize>, f64),
+    Bound(f64, OneTree, Vec<f64>),
+}
+
+/// Finds an optimal tour by depth-first branch and bound. The bound at each
+/// node is the Held-Karp bound under the node's constraints, and branching
+/// forces or forbids edges at a vertex of degree greater than 2 in its
+/// 1-tree. `initial_tour` gives the starting upper bound, typically the best
+/// tour found by the genetic algorithm.
+///
+/// The observer is told of the initial tour and of each shorter tour found,
+/// counting nodes as evaluations. If the time limit or the limit on the
+/// number of nodes is reached first, or the observer asks to stop, the best
+/// tour found is returned along with the smallest bound of any node left
+/// unexplored.
+pub fn branch_and_bound(graph: &WeightedGraph, initial_tour: &[usize],
+                        time_limit: Option<Duration>, max_nodes: usize,
+                        observer: &mut dyn Observer) -> BranchAndBoundResult {
+    let start = Instant::now();
+    let num_vertices = graph.num_vertices();
+    let mut best_tour = initial_tour.to_vec();
+    let mut best_length = match num_vertices {
+        0 => 0.0,
+        _ => crate::fitness(graph, initial_tour),
+    };
+    let event = Event::NewBest { evals: 0, tour: &best_tour, length: best_length };
+    let mut stopped = observer.notify(&event) == Signal::Stop;
+    if num_vertices <= 3 {
+        return BranchAndBoundResult {
+            best_tour,
+            best_length,
+            lower_bound: best_length,
+            optimal: true,
+            nodes: 0,
+            elapsed: start.elapsed(),
+        };
+    }
+
+    let (root_bound, root_penalties) = HeldKarp::new(graph, Some(best_length))
+        .run(ROOT_ITERATIONS);
+    let mut stack = vec![Node {
+        decisions: Vec::new(),
+        penalties: root_penalties,
+        bound: root_bound,
+    }];
+    let mut constraints = vec![Constraint::Free; num_vertices * num_vertices];
+    let mut nodes = 0;
+
+    while let Some(node) = stack.pop() {
+        if is_pruned(node.bound, best_length) {
+            continue;
+        }
+        let out_of_time = time_limit.is_some_and(|limit| start.elapsed() >= limit);
+        if stopped || nodes >= max_nodes || out_of_time {
+            let lower_bound = stack.iter()
+                .map(|node| node.bound)
+                .fold(node.bound, f64::min)
+                .min(best_length);
+            return BranchAndBoundResult {
+                best_tour,
+                best_length,
+                lower_bound,
+                optimal: false,
+                nodes,
+                elapsed: start.elapsed(),
+            };
+        }
+        nodes += 1;
+
+        if !apply(&mut constraints, num_vertices, &node.decisions) {
+            continue;
+        }
+        let (bound, tree, penalties) = match ascend(graph, &constraints, node.penalties,
+                                                    best_length, NODE_ITERATIONS) {
+            Outcome::Infeasible => continue,
+            Outcome::Tour(tour, length) => {
+                if length < best_length {
+                    best_tour = tour;
+                    best_length = length;
+                    let event = Event::NewBest { evals: nodes, tour: &best_tour, length };
+                    stopped = observer.notify(&event) == Signal::Stop;
+                }
+                continue;
+            }
+            Outcome::Bound(bound, tree, penalties) => (bound, tree, penalties),
+        };
+        if is_pruned(bound, best_length) {
+            continue;
+        }
+
+        // Branch on the free 1-tree edges at the vertex of highest degree.
+        let vertex = (0..num_vertices)
+            .max_by_key(|&v| tree.degrees[v])
+            .unwrap();
+        let num_forced = (0..num_vertices)
+            .filter(|&u| constraints[vertex * num_vertices + u] == Constraint::Forced)
+            .count();
+        let free = tree.edges
+            .iter()
+            .filter(|&&(v, u)| v == vertex || u == vertex)
+            .filter(|&&(v, u)| constraints[v * num_vertices + u] == Constraint::Free)
+            .copied()
+            .collect::<Vec<(usize, usize)>>();
+        let needed = 2 - num_forced;
+
+        // Child i forces the first i free edges and forbids the next; the
+        // last child forces as many as the vertex still needs.
+        let mut children = Vec::with_capacity(needed + 1);
+        for i in 0..=needed.min(free.len()) {
+            let mut decisions = node.decisions.clone();
+            decisions.extend(free[..i].iter().map(|&edge| (edge, Constraint::Forced)));
+            if i < needed {
+                decisions.push((free[i], Constraint::Forbidden));
+            }
+            children.push(Node {
+                decisions,
+                penalties: penalties.clone(),
+                bound,
+            });
+        }
+        stack.extend(children.into_iter().rev());
+    }
+
+    BranchAndBoundResult {
+        best_tour,
+        best_length,
+        lower_bound: best_length,
+        optimal: true,
+        nodes,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// Returns true if a node with the given bound cannot lead to a tour
+/// shorter than the best length, allowing for rounding error.
+fn is_pruned(bound: f64, best_length: f64) -> bool {
+    bound >= best_length - 1e-9 * best_length.abs().max(1.0)
+}
+
+/// Sets the constraint matrix from a node's decisions, then forbids the
+/// remaining edges at every vertex with two forced edges. Returns false if
+/// some vertex has more than two forced edges.
+fn apply(constraints: &mut [Constraint], num_vertices: usize,
+         decisions: &[((usize, usize), Constraint)]) -> bool {
+    constraints.fill(Constraint::Free);
+    for v in 0..num_vertices {
+        constraints[v * num_vertices + v] = Constraint::Forbidden;
+    }
+    for &((v, u), constraint) in decisions {
+        constraints[v * num_vertices + u] = constraint;
+        constraints[u * num_vertices + v] = constraint;
+    }
+
+    for v in 0..num_vertices {
+        let row = &mut constraints[v * num_vertices..(v + 1) * num_vertices];
+        let num_forced = row.iter().filter(|&&c| c == Constraint::Forced).count();
+        if num_forced > 2 {
+            return false;
+        }
+        if num_forced == 2 {
+            for u in 0..num_vertices {
+                if constraints[v * num_vertices + u] == Constraint::Free {
+                    constraints[v * num_vertices + u] = Constraint::Forbidden;
+                    constraints[u * num_vertices + v] = Constraint::Forbidden;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Subgradient ascent on the Held-Karp bound with edges forced into or
+/// forbidden from the 1-tree, starting from the given penalties. Stops
+/// early once the bound reaches the upper bound.
+fn ascend(graph: &WeightedGraph, constraints: &[Constraint], mut penalties: Vec<f64>,
+          upper_bound: f64, max_iterations: usize) -> Outcome {
+    let num_vertices = graph.num_vertices();
+    let num_forced = constraints.iter().filter(|&&c| c == Constraint::Forced).count() / 2;
+    let mut best: Option<(f64, OneTree, Vec<f64>)> = None;
+    let mut step_scale = 1.0;
+    let mut since_improvement = 0;
+    let patience = (max_iterations / 10).max(5);
+
+    for _ in 0..max_iterations {
+        // Forced edges are cheaper and forbidden edges dearer than any other,
+        // so the 1-tree includes as many of the former and as few of the
+        // latter as it can.
+        let tree = OneTree::build(graph, 0, |v, u| {
+            match constraints[v * num_vertices + u] {
+                Constraint::Free => graph.weight_between(v, u) + penalties[v] + penalties[u],
+                Constraint::Forced => f64::NEG_INFINITY,
+                Constraint::Forbidden => f64::INFINITY,
+            }
+        });
+        let mut forced = 0;
+        for &(v, u) in tree.edges.iter() {
+            match constraints[v * num_vertices + u] {
+                Constraint::Forced => forced += 1,
+                Constraint::Forbidden => return Outcome::Infeasible,
+                Constraint::Free => (),
+            }
+        }
+        if forced < num_forced {
+            // The forced edges contain a cycle that is not a tour.
+            return Outcome::Infeasible;
+        }
+
+        if tree.is_tour() {
+            let length = tree.edges
+                .iter()
+                .map(|&(v, u)| graph.weight_between(v, u))
+                .sum::<f64>();
+            return Outcome::Tour(tour_from_edges(num_vertices, &tree.edges), length);
+        }
+
+        let bound = tree.edges
+            .iter()
+            .map(|&(v, u)| graph.weight_between(v, u) + penalties[v] + penalties[u])
+            .sum::<f64>() - 2.0 * penalties.iter().sum::<f64>();
+        let norm = tree.degrees
+            .iter()
+            .map(|&degree| (degree as f64 - 2.0).powi(2))
+            .sum::<f64>();
+        let step = step_scale * (upper_bound - bound).max(0.0) / norm;
+
+        let next_penalties = penalties
+            .iter()
+            .zip(&tree.degrees)
+            .map(|(&penalty, &degree)| penalty + step * (degree as f64 - 2.0))
+            .collect::<Vec<f64>>();
+
+        if best.as_ref().is_none_or(|best| bound > best.0) {
+            since_improvement = 0;
+            best = Some((bound, tree, penalties));
+        } else {
+            since_improvement += 1;
+            if since_improvement >= patience {
+                step_scale /= 2.0;
+                since_improvement = 0;
+            }
+        }
+        if is_pruned(bound, upper_bound) || step == 0.0 {
+            break;
+        }
+        penalties = next_penalties;
+    }
+    let (bound, tree, penalties) = best.unwrap();
+    Outcome::Bound(bound, tree, penalties)
+}
+/// Orders the edges of a 1-tree in which every vertex has degree 2 into a
+/// tour starting at city 0.
+fn tour_from_edges(num_vertices: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut neighbors = vec![Vec::with_capacity(2); num_vertices];
+    for &(v, u) in edges {
+        neighbors[v].push(u);
+        neighbors[u].push(v);
+    }
+
+    let mut tour = vec![0];
+    let mut previous = 0;
+    let mut current = neighbors[0][0];
+    while current != 0 {
+        tour.push(current);
+        let next = match neighbors[current][0] == previous {
+            true => neighbors[current][1],
+            false => neighbors[current][0],
+        };
+        previous = current;
+        current = next;
+    }
+    tour
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::Silent;
+    use crate::weighted_graph;
+
+    fn permute(tour: &mut Vec<usize>, k: usize, visit: &mut impl FnMut(&[usize])) {
+        if k == tour.len() {
+            visit(tour);
+            return;
+        }
+        for i in k..tour.len() {
+            tour.swap(k, i);
+            permute(tour, k + 1, visit);
+            tour.swap(k, i);
+        }
+    }
+
+    #[test]
+    fn dynamic_programming_matches_brute_force() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(8, 1));
+
+        let mut optimum = f64::INFINITY;
+        let mut tour = (0..8).collect::<Vec<usize>>();
+        permute(&mut tour, 1, &mut |tour| {
+            optimum = optimum.min(crate::fitness(&graph, tour));
+        });
+
+        let (tour, length) = dynamic_programming(&graph).unwrap();
+        assert!((optimum - length).abs() < 1e-9);
+        assert!((crate::fitness(&graph, &tour) - length).abs() < 1e-9);
+    }
+
+    #[test]
+    fn large_instances_are_rejected() {
+        let points = (0..21)
+            .map(|i| (i as f64, 0.0))
+            .collect::<Vec<(f64, f64)>>();
+        assert!(dynamic_programming(&WeightedGraph::from_points(points)).is_err());
+    }
+
+    #[test]
+    fn branch_and_bound_matches_dynamic_programming() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(12, 1));
+        let (_, optimum) = dynamic_programming(&graph).unwrap();
+
+        let initial_tour = (0..12).collect::<Vec<usize>>();
+        let result = branch_and_bound(&graph, &initial_tour, None, usize::MAX, &mut Silent);
+        assert!(result.optimal);
+        assert!((result.best_length - optimum).abs() < 1e-9);
+        assert!((crate::fitness(&graph, &result.best_tour) - optimum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn time_limit_reports_a_lower_bound() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(40, 1));
+
+        let initial_tour = (0..40).collect::<Vec<usize>>();
+        let result = branch_and_bound(
+            &graph, &initial_tour, Some(Duration::ZERO), usize::MAX, &mut Silent
+        );
+        assert!(result.lower_bound > 0.0);
+        assert!(result.lower_bound <= result.best_length);
+        assert_eq!(initial_tour, result.best_tour);
+    }
+}
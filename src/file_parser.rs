@@ -30,11 +30,11 @@ fn extract_contents(file_name: &str) -> Result<String, &'static str> {
 
 fn get_num_points(line: Option<&str>) -> Result<usize, &'static str> {
     let num_points = line
-        .ok_or_else(|| "file was empty")?
+        .ok_or("file was empty")?
         .split(" : ")
         .collect::<Vec<&str>>()
         .get(1)
-        .ok_or_else(|| "first line not formatted properly")?
+        .ok_or("first line not formatted properly")?
         .trim()
         .parse::<usize>();
 
@@ -52,10 +52,10 @@ fn get_point(line: &str) -> Result<(f64, f64), &'static str> {
 
     let (x, y) = (
         point.get(1)
-            .ok_or_else(|| "x coordinate not provided")?
+            .ok_or("x coordinate not provided")?
             .parse::<f64>(),
         point.get(2)
-            .ok_or_else(|| "y coordinate not provided")?
+            .ok_or("y coordinate not provided")?
             .parse::<f64>()
     );
 
@@ -0,0 +1,198 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::f64::consts::TAU;
+use std::fmt::Write as _;
+use std::fs;
+
+/// Side of the square instances are generated in, as in the DIMACS TSP
+/// challenge generators.
+pub const SIDE: f64 = 1_000_000.0;
+
+/// The kinds of random instance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// Cities uniformly distributed over the square, at integer coordinates.
+    Uniform,
+    /// Cities normally distributed around n/10 centers placed uniformly, as
+    /// by the DIMACS `portcgen` generator, at integer coordinates.
+    Clustered,
+    /// Cities at the points of a square grid, filled row by row.
+    Grid,
+    /// Cities at random angles on a circle.
+    Circle,
+    /// Cities at random integer positions on a horizontal line.
+    Line,
+}
+
+impl Kind {
+    pub const ALL: [Self; 5] = [
+        Self::Uniform,
+        Self::Clustered,
+        Self::Grid,
+        Self::Circle,
+        Self::Line,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name.trim())
+            .ok_or("unknown instance kind")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::Clustered => "clustered",
+            Self::Grid => "grid",
+            Self::Circle => "circle",
+            Self::Line => "line",
+        }
+    }
+}
+
+/// Generates n cities of the given kind, the same ones for the same seed.
+/// The cities of grids are shuffled, so that every kind is in random order.
+pub fn generate(kind: Kind, n: usize, seed: u64) -> Vec<(f64, f64)> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let coordinate = |rng: &mut ChaCha8Rng| rng.gen_range(0.0..SIDE).floor();
+    let mut points = match kind {
+        Kind::Uniform => (0..n)
+            .map(|_| (coordinate(&mut rng), coordinate(&mut rng)))
+            .collect::<Vec<(f64, f64)>>(),
+        Kind::Clustered => {
+            let centers = (0..(n / 10).max(1))
+                .map(|_| (coordinate(&mut rng), coordinate(&mut rng)))
+                .collect::<Vec<(f64, f64)>>();
+            let std_dev = SIDE / (n as f64).sqrt();
+            (0..n)
+                .map(|_| {
+                    let (x, y) = centers[rng.gen_range(0..centers.len())];
+                    let (dx, dy) = normal_pair(&mut rng);
+                    ((x + dx * std_dev).round(), (y + dy * std_dev).round())
+                })
+                .collect()
+        }
+        Kind::Grid => {
+            let (columns, spacing) = grid_shape(n);
+            (0..n)
+                .map(|i| ((i % columns) as f64 * spacing, (i / columns) as f64 * spacing))
+                .collect()
+        }
+        Kind::Circle => (0..n)
+            .map(|_| {
+                let angle = rng.gen_range(0.0..TAU);
+                let radius = SIDE / 2.0;
+                (radius + radius * angle.cos(), radius + radius * angle.sin())
+            })
+            .collect(),
+        Kind::Line => (0..n).map(|_| (coordinate(&mut rng), SIDE / 2.0)).collect(),
+    };
+    if kind == Kind::Grid {
+        points.shuffle(&mut rng);
+    }
+    points
+}
+
+/// Returns the number of columns of the grid of n cities, the smallest square
+/// that holds them, and the distance between neighboring cities.
+fn grid_shape(n: usize) -> (usize, f64) {
+    let columns = ((n as f64).sqrt().ceil() as usize).max(1);
+    (columns, (SIDE / columns as f64).floor())
+}
+
+/// Returns two independent standard normal samples, by the Box-Muller
+/// transform.
+fn normal_pair(rng: &mut ChaCha8Rng) -> (f64, f64) {
+    let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+    let angle = rng.gen_range(0.0..TAU);
+    (radius * angle.cos(), radius * angle.sin())
+}
+
+/// Returns the optimal tour length of a generated instance, where it is
+/// known: for circles, the perimeter of the polygon visiting the cities in
+/// order of angle; for lines, twice the distance between the end cities; and
+/// for grids that are full rectangles with an even number of rows or columns,
+/// the number of cities times the spacing.
+pub fn known_optimum(kind: Kind, points: &[(f64, f64)]) -> Option<f64> {
+    let n = points.len();
+    if n < 2 {
+        return None;
+    }
+    match kind {
+        Kind::Uniform | Kind::Clustered => None,
+        Kind::Grid => {
+            let (columns, spacing) = grid_shape(n);
+            let rows = n / columns;
+            let full = rows * columns == n && rows >= 2;
+            let even = rows.is_multiple_of(2) || columns.is_multiple_of(2);
+            (full && even).then_some(n as f64 * spacing)
+        }
+        Kind::Circle => {
+            let center = SIDE / 2.0;
+            let mut by_angle = points.to_vec();
+            let angle = |point: &(f64, f64)| (point.1 - center).atan2(point.0 - center);
+            by_angle.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+            let next = by_angle.iter().cycle().skip(1);
+            Some(by_angle.iter().zip(next).map(|(a, b)| (a.0 - b.0).hypot(a.1 - b.1)).sum())
+        }
+        Kind::Line => {
+            let min = points.iter().map(|point| point.0).fold(f64::INFINITY, f64::min);
+            let max = points.iter().map(|point| point.0).fold(f64::NEG_INFINITY, f64::max);
+            Some(2.0 * (max - min))
+        }
+    }
+}
+
+/// Returns the cities in the input format: a `DIMENSION : n` line, a
+/// `NODE X Y` header and a numbered line per city.
+pub fn format_instance(points: &[(f64, f64)]) -> String {
+    let mut out = format!("DIMENSION : {}\nNODE X Y\n", points.len());
+    for (i, (x, y)) in points.iter().enumerate() {
+        writeln!(out, "{} {} {}", i + 1, x, y).unwrap();
+    }
+    out
+}
+
+/// Writes the cities to the named file in the input format.
+pub fn write_instance(file_name: &str, points: &[(f64, f64)]) -> Result<(), &'static str> {
+    fs::write(file_name, format_instance(points))
+        .map_err(|_| "error trying to write instance file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exact;
+    use crate::file_parser;
+    use crate::weighted_graph::WeightedGraph;
+
+    #[test]
+    fn instances_are_reproducible_and_readable() {
+        for kind in Kind::ALL {
+            let points = generate(kind, 50, 9);
+            assert_eq!(points, generate(kind, 50, 9), "{}", kind.name());
+            assert_ne!(points, generate(kind, 50, 10), "{}", kind.name());
+
+            let file_name = std::env::temp_dir()
+                .join(format!("tsp-generated-{}-{}.txt", kind.name(), std::process::id()));
+            let file_name = file_name.to_str().unwrap();
+            write_instance(file_name, &points).unwrap();
+            let read = file_parser::parse_file(file_name).unwrap();
+            fs::remove_file(file_name).unwrap();
+            assert_eq!(points, read, "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn known_optima_are_optimal() {
+        for (kind, n) in [(Kind::Circle, 11), (Kind::Line, 10), (Kind::Grid, 12)] {
+            let points = generate(kind, n, 4);
+            let optimum = known_optimum(kind, &points).unwrap();
+            let (_, length) = exact::dynamic_programming(&WeightedGraph::from_points(points))
+                .unwrap();
+            assert!((optimum - length).abs() < 1e-6 * length, "{}", kind.name());
+        }
+        assert_eq!(None, known_optimum(Kind::Grid, &generate(Kind::Grid, 10, 4)));
+    }
+}
@@ -2,21 +2,25 @@ mod utils;
 
 use rand::prelude::*;
 use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
 use std::mem;
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+
+use crate::weighted_graph::WeightedGraph;
 
 /// Represents a permutation genotype.
 /// The elements are in the range 0..N-1 where N is the number of alleles.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Genotype {
     data: Vec<usize>,
 }
 
 impl Genotype {
-    pub fn random(num_alleles: usize, rng: &mut ThreadRng) -> Self {
+    pub fn random(num_alleles: usize, rng: &mut ChaCha8Rng) -> Self {
         if num_alleles == 0 {
             panic!("number of alleles may not be 0");
         }
-    
+
         let mut data = (0..num_alleles).collect::<Vec<usize>>();
         data.shuffle(rng);
 
@@ -25,6 +29,30 @@ impl Genotype {
         }
     }
 
+    /// Wraps an existing tour. Panics if data is not a permutation of
+    /// 0..N-1.
+    pub fn from_data(data: Vec<usize>) -> Self {
+        if !Self::is_permutation(&data) {
+            panic!("genotype must be a permutation");
+        }
+
+        Self {
+            data,
+        }
+    }
+
+    /// Returns whether data is a permutation of 0..N-1, and so a valid tour.
+    pub fn is_permutation(data: &[usize]) -> bool {
+        let mut seen = vec![false; data.len()];
+        for &allele in data.iter() {
+            if allele >= data.len() || seen[allele] {
+                return false;
+            }
+            seen[allele] = true;
+        }
+        true
+    }
+
     pub fn data(&self) -> &[usize] {
         &self.data
     }
@@ -39,35 +67,212 @@ impl Genotype {
         self.data[pos]
     }
 
-    pub fn inversion_mutation(self, rng: &mut ThreadRng) -> Self {
-        let mut clone = self.data.clone();
-        let num_alleles = clone.len();
+    /// Returns the tour as a sequence starting at city 0 and heading towards
+    /// the smaller of its two neighbours, so that every rotation and
+    /// reflection of the same tour has the same canonical form.
+    pub fn canonical(&self) -> Vec<usize> {
+        let num_alleles = self.data.len();
+        let start = self.data.iter().position(|&x| x == 0).unwrap();
+        let next = self.data[(start + 1) % num_alleles];
+        let prev = self.data[(start + num_alleles - 1) % num_alleles];
 
-        let mut pos1 = rng.gen_range(0..num_alleles);
-        let mut pos2 = rng.gen_range(0..num_alleles);
-        if pos1 > pos2 {
-            mem::swap(&mut pos1, &mut pos2);
+        if next <= prev {
+            (0..num_alleles)
+                .map(|i| self.data[(start + i) % num_alleles])
+                .collect()
+        } else {
+            (0..num_alleles)
+                .map(|i| self.data[(start + num_alleles - i) % num_alleles])
+                .collect()
+        }
+    }
+
+    /// Returns true if both genotypes represent the same tour, regardless of
+    /// starting city or direction.
+    pub fn same_tour(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+
+    /// Returns the number of edges in this tour that are not in the other.
+    pub fn bond_distance(&self, other: &Self) -> usize {
+        let num_alleles = self.data.len();
+        if num_alleles != other.data.len() {
+            panic!("bond distance requires both genotypes to have equal size");
         }
 
-        let _ = &clone[pos1..pos2].reverse();
+        let mut neighbours = vec![(0, 0); num_alleles];
+        for i in 0..num_alleles {
+            neighbours[other.data[i]] = (
+                other.data[(i + num_alleles - 1) % num_alleles],
+                other.data[(i + 1) % num_alleles],
+            );
+        }
+
+        (0..num_alleles)
+            .filter(|&i| {
+                let (v, u) = (self.data[i], self.data[(i + 1) % num_alleles]);
+                neighbours[v].0 != u && neighbours[v].1 != u
+            })
+            .count()
+    }
+
+    pub fn inversion_mutation(self, rng: &mut ChaCha8Rng) -> Self {
+        let mv = Move::random_inversion(self.data.len(), rng);
+        self.with_move(mv)
+    }
+
+    pub fn swap_mutation(self, rng: &mut ChaCha8Rng) -> Self {
+        let mv = Move::random_swap(self.data.len(), rng);
+        self.with_move(mv)
+    }
+
+    /// Moves the allele at one random position to another random position,
+    /// shifting the alleles in between along by one.
+    pub fn insert_mutation(self, rng: &mut ChaCha8Rng) -> Self {
+        let mv = Move::random_insert(self.data.len(), rng);
+        self.with_move(mv)
+    }
+
+    /// Cuts the tour into four parts A B C D at random and reconnects them
+    /// as A C B D. The kick changes four edges in a way that 2-opt and
+    /// Or-opt moves cannot easily undo. Tours of fewer than 8 cities are
+    /// left unchanged.
+    pub fn double_bridge(self, rng: &mut ChaCha8Rng) -> Self {
+        let num_alleles = self.data.len();
+        if num_alleles < 8 {
+            return self;
+        }
+        let mut cuts = rand::seq::index::sample(rng, num_alleles - 1, 3)
+            .into_iter()
+            .map(|cut| cut + 1)
+            .collect::<Vec<usize>>();
+        cuts.sort();
+
+        let data = &self.data;
+        let mut kicked = Vec::with_capacity(num_alleles);
+        kicked.extend_from_slice(&data[..cuts[0]]);
+        kicked.extend_from_slice(&data[cuts[1]..cuts[2]]);
+        kicked.extend_from_slice(&data[cuts[0]..cuts[1]]);
+        kicked.extend_from_slice(&data[cuts[2]..]);
 
         Self {
-            data: clone,
+            data: kicked,
+        }
+    }
+
+    fn with_move(mut self, mv: Move) -> Self {
+        self.apply_move(mv);
+        self
+    }
+
+    /// Applies a move in place.
+    pub fn apply_move(&mut self, mv: Move) {
+        match mv {
+            Move::Swap(pos1, pos2) => self.data.swap(pos1, pos2),
+            Move::Inversion(pos1, pos2) => self.data[pos1..pos2].reverse(),
+            Move::Insert(from, to) => {
+                let allele = self.data.remove(from);
+                self.data.insert(to, allele);
+            }
         }
     }
 
-    pub fn swap_mutation(self, rng: &mut ThreadRng) -> Self {
-        let mut clone = self.data.clone();
-        let num_alleles = clone.len();
+    /// Returns the change in tour length that applying the move would make,
+    /// looking only at the edges it breaks and joins.
+    pub fn move_delta(&self, mv: Move, graph: &WeightedGraph) -> f64 {
+        let data = &self.data;
+        let num_alleles = data.len();
+        if num_alleles < 4 {
+            // Every tour of three or fewer cities has the same length.
+            return 0.0;
+        }
+        let at = |pos: usize| data[pos % num_alleles];
+        let weight = |v: usize, u: usize| graph.weight_between(v, u);
+
+        match mv {
+            Move::Inversion(pos1, pos2) => {
+                if pos2 - pos1 < 2 || pos2 - pos1 == num_alleles {
+                    return 0.0;
+                }
+                let (before, first) = (at(pos1 + num_alleles - 1), data[pos1]);
+                let (last, after) = (data[pos2 - 1], at(pos2));
+                weight(before, last) + weight(first, after)
+                    - weight(before, first) - weight(last, after)
+            }
+            Move::Swap(pos1, pos2) => {
+                if pos1 == pos2 {
+                    return 0.0;
+                }
+                let swapped = |pos: usize| match pos % num_alleles {
+                    p if p == pos1 => data[pos2],
+                    p if p == pos2 => data[pos1],
+                    p => data[p],
+                };
+                // Each edge is named by the position it starts from.
+                let mut edges = [pos1, pos2, pos1 + num_alleles - 1, pos2 + num_alleles - 1]
+                    .map(|pos| pos % num_alleles);
+                edges.sort();
+                let mut delta = 0.0;
+                for (i, &pos) in edges.iter().enumerate() {
+                    if i > 0 && edges[i - 1] == pos {
+                        continue;
+                    }
+                    delta += weight(swapped(pos), swapped(pos + 1))
+                        - weight(at(pos), at(pos + 1));
+                }
+                delta
+            }
+            Move::Insert(from, to) => {
+                let allele = data[from];
+                let (prev, next) = (at(from + num_alleles - 1), at(from + 1));
+                // Positions in the tour once the allele has been removed.
+                let removed = |pos: usize| {
+                    let pos = pos % (num_alleles - 1);
+                    data[if pos < from { pos } else { pos + 1 }]
+                };
+                let (left, right) = (removed(to + num_alleles - 2), removed(to));
+                weight(prev, next) - weight(prev, allele) - weight(allele, next)
+                    + weight(left, allele) + weight(allele, right) - weight(left, right)
+            }
+        }
+    }
+
+    /// Constructs a child genotype according to the order crossover algorithm.
+    /// A random segment is copied from the first parent, and the remaining
+    /// positions are filled, starting after the segment, with the missing
+    /// alleles in the order they appear in the second parent.
+    pub fn order_crossover(parent1: &Self, parent2: &Self,
+                           rng: &mut ChaCha8Rng) -> Self {
+        let parent1 = parent1.data();
+        let parent2 = parent2.data();
+        if parent1.len() != parent2.len() {
+            panic!("crossover requires both genotypes to have equal size");
+        }
+        let num_alleles = parent1.len();
 
         let mut pos1 = rng.gen_range(0..num_alleles);
         let mut pos2 = rng.gen_range(0..num_alleles);
-        
-        clone.swap(pos1, pos2);
-        
-        Self {
-            data: clone,
+        if pos1 > pos2 {
+            mem::swap(&mut pos1, &mut pos2);
+        }
+
+        let mut child = vec![0; num_alleles];
+        let mut in_child = vec![false; num_alleles];
+        for i in pos1..=pos2 {
+            child[i] = parent1[i];
+            in_child[parent1[i]] = true;
+        }
+
+        let mut child_idx = (pos2 + 1) % num_alleles;
+        for offset in 1..=num_alleles {
+            let allele = parent2[(pos2 + offset) % num_alleles];
+            if !in_child[allele] {
+                child[child_idx] = allele;
+                child_idx = (child_idx + 1) % num_alleles;
+            }
         }
+
+        Genotype { data: child }
     }
 
     /// Constructs a child genotype according to the edge crossover algorithm.
@@ -76,7 +281,7 @@ impl Genotype {
     /// accepting those found in one parent or the other, and finally 
     /// resorting to random edges in case the above two cases fail.
     pub fn edge_crossover(parent1: &Self, parent2: &Self, 
-                          rng: &mut ThreadRng) -> Self {
+                          rng: &mut ChaCha8Rng) -> Self {
         let parent1 = parent1.data();
         let parent2 = parent2.data();
         if parent1.len() != parent2.len() {
@@ -84,15 +289,15 @@ impl Genotype {
         }
         let num_alleles = parent1.len();
 
-        let mut edge_table = utils::construct_edge_table(&parent1, &parent2); 
+        let mut edge_table = utils::construct_edge_table(parent1, parent2); 
         let mut child = Vec::with_capacity(num_alleles);
 
         let mut vertex = Some(rng.gen_range(0..num_alleles));
 
         // Random vertices that have not yet been added. Used if following 
         // edges leads to a dead-end.
-        let mut not_removed: HashSet<usize> = 
-            HashSet::from_iter((0..num_alleles));
+        let mut not_removed: BTreeSet<usize> = 
+            BTreeSet::from_iter(0..num_alleles);
 
         let mut allele = vertex.unwrap(); // literally cannot be None
         child.push(allele);
@@ -105,7 +310,7 @@ impl Genotype {
 
         while child.len() != num_alleles { 
             vertex = utils::try_select_adjacent(&edge_table, allele, rng);
-            while let None = vertex {
+            while vertex.is_none() {
                 if allele_try_idx < child.len() {
                     vertex = utils::try_select_adjacent(
                         &edge_table, child[allele_try_idx], rng
@@ -130,3 +335,103 @@ impl Genotype {
         println!("{:?}", self.data);
     }
 }
+
+/// A change to a tour at given positions, as made by the mutation
+/// operators. Applying a move via Genotype::apply_move and pricing it via
+/// Genotype::move_delta lets local search avoid evaluating whole tours.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Move {
+    /// Swaps the alleles at two positions.
+    Swap(usize, usize),
+    /// Reverses the alleles from the first position up to but not including
+    /// the second.
+    Inversion(usize, usize),
+    /// Removes the allele at the first position and reinserts it at the
+    /// second.
+    Insert(usize, usize),
+}
+
+impl Move {
+    pub fn random_swap(num_alleles: usize, rng: &mut ChaCha8Rng) -> Self {
+        Self::Swap(rng.gen_range(0..num_alleles), rng.gen_range(0..num_alleles))
+    }
+
+    pub fn random_inversion(num_alleles: usize, rng: &mut ChaCha8Rng) -> Self {
+        let mut pos1 = rng.gen_range(0..num_alleles);
+        let mut pos2 = rng.gen_range(0..num_alleles);
+        if pos1 > pos2 {
+            mem::swap(&mut pos1, &mut pos2);
+        }
+        Self::Inversion(pos1, pos2)
+    }
+
+    pub fn random_insert(num_alleles: usize, rng: &mut ChaCha8Rng) -> Self {
+        Self::Insert(rng.gen_range(0..num_alleles), rng.gen_range(0..num_alleles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_permutation(genotype: &Genotype) -> bool {
+        let mut sorted = genotype.data().to_vec();
+        sorted.sort();
+        sorted == (0..genotype.num_alleles()).collect::<Vec<usize>>()
+    }
+
+    #[test]
+    fn operators_produce_permutations() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        for _ in 0..100 {
+            let parent1 = Genotype::random(10, &mut rng);
+            let parent2 = Genotype::random(10, &mut rng);
+
+            assert!(is_permutation(&Genotype::order_crossover(
+                &parent1, &parent2, &mut rng
+            )));
+            assert!(is_permutation(&parent1.clone().insert_mutation(&mut rng)));
+            assert!(is_permutation(&parent1.double_bridge(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn move_delta_matches_change_in_length() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let points = (0..9)
+            .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+
+        for _ in 0..300 {
+            let genotype = Genotype::random(9, &mut rng);
+            for mv in [
+                Move::random_swap(9, &mut rng),
+                Move::random_inversion(9, &mut rng),
+                Move::random_insert(9, &mut rng),
+            ] {
+                let mut moved = genotype.clone();
+                moved.apply_move(mv);
+                let change = crate::fitness(&graph, moved.data())
+                    - crate::fitness(&graph, genotype.data());
+                assert!((genotype.move_delta(mv, &graph) - change).abs() < 1e-9, "{:?}", mv);
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_and_reflections_are_the_same_tour() {
+        let tour = Genotype { data: vec![2, 0, 3, 1, 4] };
+        let rotated = Genotype { data: vec![1, 4, 2, 0, 3] };
+        let reflected = Genotype { data: vec![4, 1, 3, 0, 2] };
+        let different = Genotype { data: vec![0, 1, 2, 3, 4] };
+
+        assert_eq!(vec![0, 2, 4, 1, 3], tour.canonical());
+        assert!(tour.same_tour(&rotated));
+        assert!(tour.same_tour(&reflected));
+        assert!(!tour.same_tour(&different));
+
+        assert_eq!(0, tour.bond_distance(&reflected));
+        assert_eq!(5, tour.bond_distance(&different));
+    }
+}
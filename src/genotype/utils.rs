@@ -1,13 +1,12 @@
 use super::*;
 
-use std::collections::HashMap;
-use rand::thread_rng;
+use std::collections::BTreeMap;
 
 pub fn construct_edge_table(parent1: &[usize], parent2: &[usize]) 
--> Vec<HashMap<usize, usize>> {
+-> Vec<BTreeMap<usize, usize>> {
     let num_alleles = parent1.len();
 
-    let mut edge_table = vec![HashMap::with_capacity(4); num_alleles];
+    let mut edge_table = vec![BTreeMap::new(); num_alleles];
 
     for i in 0..num_alleles-1 {
         add_to_edge_table(&mut edge_table, parent1[i], parent1[i+1]);
@@ -23,7 +22,7 @@ pub fn construct_edge_table(parent1: &[usize], parent2: &[usize])
 
 /// Inserts an edge into the edge table, increasing its multiplicity count if
 /// the edge has already been inserted, or initializing it to 1 if it hasn't.
-fn add_to_edge_table(edge_table: &mut Vec<HashMap<usize, usize>>, 
+fn add_to_edge_table(edge_table: &mut [BTreeMap<usize, usize>], 
                      v: usize, u: usize) {
     let count = edge_table[v].entry(u).or_insert(0);
     *count += 1;
@@ -36,8 +35,8 @@ fn add_to_edge_table(edge_table: &mut Vec<HashMap<usize, usize>>,
 /// First tries to find an adjacent vertex common to both parents. Then tries
 /// to find any adjacent vertex which itself has the smallest non-zero length
 /// edge list. If it finds only empty lists, it returns None.
-pub fn try_select_adjacent(edge_table: &Vec<HashMap<usize, usize>>, 
-                        vertex: usize, rng: &mut ThreadRng) -> Option<usize> {
+pub fn try_select_adjacent(edge_table: &[BTreeMap<usize, usize>], 
+                        vertex: usize, rng: &mut ChaCha8Rng) -> Option<usize> {
     let row = &edge_table[vertex];
 
     // Attempt 1: Try to find edge common to both parents.
@@ -66,7 +65,7 @@ pub fn try_select_adjacent(edge_table: &Vec<HashMap<usize, usize>>,
     None
 }
 
-pub fn select_random(candidates: &HashSet<usize>, rng: &mut ThreadRng) -> usize {
+pub fn select_random(candidates: &BTreeSet<usize>, rng: &mut ChaCha8Rng) -> usize {
     let idx = rng.gen_range(0..candidates.len());
     let allele = *candidates
         .iter()
@@ -78,7 +77,7 @@ pub fn select_random(candidates: &HashSet<usize>, rng: &mut ThreadRng) -> usize
 /// Removes the specified vertex from the adjacency lists of each vertex.
 /// Does NOT remove the vertex as an index into the table, that is, vertices
 /// adjacent to it can still be found.
-pub fn remove_edge(edge_table: &mut Vec<HashMap<usize, usize>>, vertex: usize) {
+pub fn remove_edge(edge_table: &mut [BTreeMap<usize, usize>], vertex: usize) {
     for list in edge_table.iter_mut() {
         list.remove(&vertex);
     }
@@ -90,13 +89,11 @@ mod tests {
 
     #[test]
     fn edge_table_is_correctly_constructed() {
-        let mut rng = rand::thread_rng();
-
         // All edges in permutations of size three are adjacent to each other.
         let parent1 = vec![0, 1, 2];
         let parent2 = vec![2, 1, 0];
 
-        let mut edge_table = construct_edge_table(&parent1, &parent2);
+        let edge_table = construct_edge_table(&parent1, &parent2);
 
         assert_eq!(2, edge_table[0].len());
         assert_eq!(2, *edge_table[0].get(&1).unwrap());
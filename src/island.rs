@@ -0,0 +1,384 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::genotype::Genotype;
+use crate::observer::{Event, Observer, Signal};
+use crate::result::RunResult;
+use crate::solver::Budget;
+use crate::termination::StopReason;
+use crate::weighted_graph::WeightedGraph;
+
+/// Which islands each island sends its emigrants to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    /// The next island, with the last sending to the first.
+    Ring,
+    /// Every other island.
+    FullyConnected,
+    /// Another island chosen at random at each migration.
+    Random,
+}
+
+impl Topology {
+    pub const ALL: [Self; 3] = [Self::Ring, Self::FullyConnected, Self::Random];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|topology| topology.name() == name.trim())
+            .ok_or("unknown topology")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ring => "ring",
+            Self::FullyConnected => "full",
+            Self::Random => "random",
+        }
+    }
+
+    /// Returns the islands that island `index` of `num_islands` sends to.
+    fn targets(self, index: usize, num_islands: usize, rng: &mut ChaCha8Rng) -> Vec<usize> {
+        let others = (0..num_islands).filter(|&other| other != index);
+        match self {
+            Self::Ring => vec![(index + 1) % num_islands],
+            Self::FullyConnected => others.collect(),
+            Self::Random => others.choose(rng).into_iter().collect(),
+        }
+    }
+}
+
+/// Which members of the population emigrate. Emigrants are copies, so the
+/// population they leave is unchanged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emigrants {
+    Best,
+    Random,
+}
+
+impl Emigrants {
+    pub const ALL: [Self; 2] = [Self::Best, Self::Random];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.name() == name.trim())
+            .ok_or("unknown emigrant policy")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            Self::Random => "random",
+        }
+    }
+}
+
+/// Which members of the population immigrants replace. The best member is
+/// never replaced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Immigrants {
+    Worst,
+    Random,
+}
+
+impl Immigrants {
+    pub const ALL: [Self; 2] = [Self::Worst, Self::Random];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.name() == name.trim())
+            .ok_or("unknown immigrant policy")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Worst => "worst",
+            Self::Random => "random",
+        }
+    }
+}
+
+/// How populations on different islands exchange members.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Migration {
+    pub topology: Topology,
+    /// Evaluations on each island between migrations.
+    pub interval: usize,
+    /// Members each island sends to each of its targets.
+    pub size: usize,
+    pub emigrants: Emigrants,
+    pub immigrants: Immigrants,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        Self {
+            topology: Topology::Ring,
+            interval: 1000,
+            size: 2,
+            emigrants: Emigrants::Best,
+            immigrants: Immigrants::Worst,
+        }
+    }
+}
+
+type Migrants = Vec<(Genotype, f64)>;
+
+/// One island's connections to the others.
+pub(crate) struct Island {
+    index: usize,
+    migration: Migration,
+    /// Senders to every island, including this one.
+    senders: Vec<Sender<Migrants>>,
+    receiver: Receiver<Migrants>,
+}
+
+impl Island {
+    pub(crate) fn interval(&self) -> usize {
+        self.migration.interval
+    }
+
+    /// Sends emigrants to the island's targets, then lets in any immigrants
+    /// that have arrived, keeping the population sorted by fitness. Islands
+    /// never wait for each other, and migrants sent to an island that has
+    /// already stopped are lost.
+    pub(crate) fn migrate(&self, population: &mut [(Genotype, f64)], rng: &mut ChaCha8Rng) {
+        let migration = self.migration;
+        let size = migration.size.min(population.len());
+        for target in migration.topology.targets(self.index, self.senders.len(), rng) {
+            let emigrants = match migration.emigrants {
+                Emigrants::Best => population[..size].to_vec(),
+                Emigrants::Random => population.choose_multiple(rng, size).cloned().collect(),
+            };
+            let _ = self.senders[target].send(emigrants);
+        }
+
+        let mut immigrants = self.receiver.try_iter().flatten().collect::<Migrants>();
+        immigrants.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+        immigrants.truncate(population.len() - 1);
+        let mut replaceable = (1..population.len()).collect::<Vec<usize>>();
+        match migration.immigrants {
+            Immigrants::Worst => replaceable.reverse(),
+            Immigrants::Random => replaceable.shuffle(rng),
+        }
+        for (immigrant, index) in immigrants.into_iter().zip(replaceable) {
+            population[index] = immigrant;
+        }
+        population.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+    }
+}
+
+/// What an island tells the thread that combines the islands' results.
+enum Report {
+    Bound(f64),
+    NewBest { island: usize, evals: usize, tour: Vec<usize>, length: f64 },
+    Progress { island: usize, evals: usize },
+    Stop { island: usize, evals: usize, reason: StopReason },
+}
+
+/// Observes one island, passing on what the combined run reports and
+/// stopping the island once the combined run is to stop.
+struct Relay<'a> {
+    island: usize,
+    sender: Sender<Report>,
+    stop: &'a AtomicBool,
+}
+
+impl Observer for Relay<'_> {
+    fn notify(&mut self, event: &Event) -> Signal {
+        let island = self.island;
+        let report = match *event {
+            Event::Bound { value } if island == 0 => Some(Report::Bound(value)),
+            Event::NewBest { evals, tour, length } =>
+                Some(Report::NewBest { island, evals, tour: tour.to_vec(), length }),
+            Event::Progress { evals, .. } => Some(Report::Progress { island, evals }),
+            Event::Stop { evals, reason, .. } => Some(Report::Stop { island, evals, reason }),
+            _ => None,
+        };
+        if let Some(report) = report {
+            let _ = self.sender.send(report);
+        }
+        match self.stop.load(Ordering::Relaxed) {
+            true => Signal::Stop,
+            false => Signal::Continue,
+        }
+    }
+}
+
+/// Runs the genetic algorithm on `config.islands` populations of
+/// `config.pop_size`, each on its own thread, which share the evaluation
+/// budget equally and exchange members as `config.migration` describes.
+/// Each island stops on its own conditions, except that all stop once one
+/// reaches the target length or the allowed gap, or the observer asks to
+/// stop.
+///
+/// The observer is told of the best tour across all islands and of the
+/// evaluations used by all of them together; the islands' statistics and
+/// phase changes are not reported. The result is that of the island with
+/// the best tour, with the evaluations of all islands.
+pub fn evolve_islands(config: &Config, graph: &WeightedGraph, budget: &Budget,
+                      observer: &mut dyn Observer) -> RunResult {
+    let start = Instant::now();
+    let num_islands = config.islands;
+    let stop = AtomicBool::new(false);
+    let (report_sender, reports) = mpsc::channel();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..num_islands)
+        .map(|_| mpsc::channel())
+        .unzip();
+
+    let results = thread::scope(|scope| {
+        let handles = receivers.into_iter().enumerate()
+            .map(|(index, receiver)| {
+                let island = Island {
+                    index,
+                    migration: config.migration,
+                    senders: senders.clone(),
+                    receiver,
+                };
+                let mut relay = Relay { island: index, sender: report_sender.clone(), stop: &stop };
+                let budget = Budget {
+                    num_evals: budget.num_evals / num_islands
+                        + usize::from(index < budget.num_evals % num_islands),
+                    termination: budget.termination.clone(),
+                };
+                // Islands draw from separate streams of the seeded generator.
+                let mut rng = config.rng();
+                rng.set_stream(index as u64);
+                scope.spawn(move || {
+                    crate::evolve_on_island(config, graph, &budget, &mut relay, rng, Some(&island))
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(report_sender);
+
+        let mut island_evals = vec![0; num_islands];
+        let mut best = f64::INFINITY;
+        let mut combined_stop = None;
+        for report in reports.iter() {
+            let prev_evals = island_evals.iter().sum::<usize>();
+            let signal = match report {
+                Report::Bound(value) => observer.notify(&Event::Bound { value }),
+                Report::NewBest { island, evals, tour, length } => {
+                    island_evals[island] = evals;
+                    match length < best {
+                        true => {
+                            best = length;
+                            let evals = island_evals.iter().sum();
+                            observer.notify(&Event::NewBest { evals, tour: &tour, length })
+                        }
+                        false => Signal::Continue,
+                    }
+                }
+                Report::Progress { island, evals } => {
+                    island_evals[island] = evals;
+                    let evals = island_evals.iter().sum::<usize>();
+                    match evals / 10 > prev_evals / 10 {
+                        true => observer.notify(&Event::Progress { evals, best }),
+                        false => Signal::Continue,
+                    }
+                }
+                Report::Stop { island, evals, reason } => {
+                    island_evals[island] = evals;
+                    if matches!(reason, StopReason::TargetLength | StopReason::LowerBoundGap) {
+                        combined_stop.get_or_insert(reason);
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                    Signal::Continue
+                }
+            };
+            if signal == Signal::Stop {
+                combined_stop.get_or_insert(StopReason::Cancelled);
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+
+        let results = handles.into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<RunResult>>();
+        (results, combined_stop)
+    });
+    let (results, combined_stop) = results;
+
+    let evaluations = results.iter().map(|result| result.evaluations).sum();
+    // Without a reason to stop them all, the run lasts until the last
+    // island stops.
+    let stop_reason = combined_stop.unwrap_or_else(|| {
+        results.iter().max_by_key(|result| result.elapsed).unwrap().stop_reason
+    });
+    let best = results.into_iter()
+        .min_by(|x, y| x.best_length.partial_cmp(&y.best_length).unwrap())
+        .unwrap();
+    crate::notify_stop(observer, evaluations, stop_reason, best.best_length, best.lower_bound);
+
+    RunResult {
+        evaluations,
+        elapsed: start.elapsed(),
+        stop_reason,
+        ..best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::Silent;
+    use crate::weighted_graph;
+
+    fn population(lengths: &[f64], num_alleles: usize) -> Vec<(Genotype, f64)> {
+        let mut rng = ChaCha8Rng::from_entropy();
+        lengths.iter()
+            .map(|&length| (Genotype::random(num_alleles, &mut rng), length))
+            .collect()
+    }
+
+    #[test]
+    fn immigrants_replace_the_worst_members() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..2).map(|_| mpsc::channel()).unzip();
+        let mut islands = receivers.into_iter().enumerate()
+            .map(|(index, receiver)| Island {
+                index,
+                migration: Migration::default(),
+                senders: senders.clone(),
+                receiver,
+            })
+            .collect::<Vec<Island>>();
+
+        let mut sender = population(&[1.0, 2.0, 3.0, 4.0], 10);
+        let mut receiver = population(&[5.0, 6.0, 7.0, 8.0], 10);
+        islands[0].migrate(&mut sender, &mut rng);
+        islands.remove(0);
+        islands[0].migrate(&mut receiver, &mut rng);
+
+        let lengths = receiver.iter().map(|member| member.1).collect::<Vec<f64>>();
+        assert_eq!(vec![1.0, 2.0, 5.0, 6.0], lengths);
+        assert_eq!(sender[0].0, receiver[0].0);
+    }
+
+    #[test]
+    fn islands_share_the_budget() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(30, 1));
+
+        for topology in Topology::ALL {
+            let args = [
+                "tsp", "unused", "10", "4001", "--seed", "1",
+                "--islands", "4", "--topology", topology.name(),
+                "--migration_interval", "100", "--emigrants", "random", "--immigrants", "random",
+            ];
+            let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            let budget = Budget::from_config(&config);
+            let result = evolve_islands(&config, &graph, &budget, &mut Silent);
+
+            assert_eq!(4001, result.evaluations);
+            assert_eq!(StopReason::Evaluations, result.stop_reason);
+            assert!((crate::fitness(&graph, &result.best_tour) - result.best_length).abs() < 1e-6);
+        }
+    }
+}
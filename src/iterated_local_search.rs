@@ -0,0 +1,173 @@
+use crate::config::Config;
+use crate::diversity::Diversity;
+use crate::genotype::Genotype;
+use crate::local_search::LocalSearch;
+use crate::observer::{Event, Observer, PhaseChange, Statistics};
+use crate::result::RunResult;
+use crate::solver::Budget;
+use crate::termination::StopReason;
+use crate::weighted_graph::WeightedGraph;
+
+/// Which of the current tour and its kicked and improved successor the
+/// search continues from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Acceptance {
+    /// The successor, if it is no longer than the current tour.
+    Better,
+    /// Always the successor.
+    RandomWalk,
+    /// As Better, but starts again from a new random tour once the best
+    /// tour has not improved for `after` iterations.
+    Restart { after: usize },
+}
+
+impl Acceptance {
+    /// Parses a criterion of the form `better`, `random-walk` or
+    /// `restart:<iterations>`.
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let (name, param) = match spec.trim().split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (spec.trim(), None),
+        };
+        let param = match param {
+            None => None,
+            Some(param) => Some(param.parse::<usize>()
+                .map_err(|_| "could not parse acceptance parameter")?),
+        };
+
+        match (name, param) {
+            ("better", None) => Ok(Self::Better),
+            ("random-walk", None) => Ok(Self::RandomWalk),
+            ("restart", Some(after)) if after > 0 => Ok(Self::Restart { after }),
+            _ => Err("unknown acceptance criterion"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Better => "better",
+            Self::RandomWalk => "random-walk",
+            Self::Restart { .. } => "restart",
+        }
+    }
+}
+
+/// Runs iterated local search on the given graph. The tour built as the
+/// GA would build the first member of its initial population is improved by
+/// local search; then each iteration kicks the current tour with a double
+/// bridge, improves the result by local search, and keeps one of the two by
+/// the acceptance criterion. Each improved tour costs one evaluation. The
+/// local search is `config.local_search`, or 2-opt with Or-opt if none is
+/// set.
+pub fn iterated_local_search(config: &Config, graph: &WeightedGraph, budget: &Budget,
+                             observer: &mut dyn Observer) -> RunResult {
+    let mut rng = config.rng();
+    let num_vertices = graph.num_vertices();
+    let num_evals = budget.num_evals;
+    let candidates = graph.nearest_neighbors(config.candidates);
+    let search = config.local_search.unwrap_or(LocalSearch::TwoOptOrOpt);
+    let improve = |genotype: Genotype| {
+        let mut tour = genotype.data().to_vec();
+        search.apply(&mut tour, graph, &candidates);
+        let length = crate::fitness(graph, &tour);
+        (Genotype::from_data(tour), length)
+    };
+
+    let initial = crate::initial_population(
+        1, num_vertices, &config.seeding, graph, &mut rng
+    ).remove(0);
+    let mut current = improve(initial.0);
+    let mut best = current.clone();
+
+    let criteria = crate::termination_criteria(config, budget, graph, best.1, observer);
+    let mut termination = criteria.start(num_evals, best.1);
+    crate::notify_new_best(observer, &mut termination, 0, &best);
+    let mut last_improvement = 0;
+    let mut iteration = 0;
+    let mut evals = 0;
+    let stop_reason: StopReason;
+    loop {
+        if let Some(reason) = termination.check(evals, best.1) {
+            stop_reason = reason;
+            break;
+        }
+        iteration += 1;
+
+        let candidate = improve(current.0.clone().double_bridge(&mut rng));
+        let prev_evals = evals;
+        evals += 1;
+        if candidate.1 < best.1 {
+            best = candidate.clone();
+            last_improvement = iteration;
+            crate::notify_new_best(observer, &mut termination, evals, &best);
+        }
+
+        current = match config.acceptance {
+            Acceptance::RandomWalk => candidate,
+            Acceptance::Restart { after } if iteration - last_improvement >= after => {
+                last_improvement = iteration;
+                let change = PhaseChange::Restart;
+                crate::notify(observer, &mut termination, Event::PhaseChange { evals, change });
+                improve(Genotype::random(num_vertices, &mut rng))
+            }
+            Acceptance::Better | Acceptance::Restart { .. } => {
+                if candidate.1 <= current.1 { candidate } else { current }
+            }
+        };
+
+        if evals / 10 > prev_evals / 10 {
+            let best = best.1;
+            crate::notify(observer, &mut termination, Event::Progress { evals, best });
+        }
+        let interval = config.diversity_interval;
+        if interval > 0 && evals / interval > prev_evals / interval {
+            let statistics = Statistics::IteratedLocalSearch { current: current.1 };
+            crate::notify(observer, &mut termination, Event::Statistics { evals, statistics });
+        }
+    }
+
+    crate::notify_stop(observer, evals, stop_reason, best.1, criteria.lower_bound);
+
+    RunResult {
+        best_tour: best.0.data().to_vec(),
+        best_length: best.1,
+        evaluations: evals,
+        elapsed: termination.elapsed(),
+        stop_reason,
+        diversity: Diversity::measure(&[best]),
+        lower_bound: criteria.lower_bound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::Silent;
+    use crate::exact;
+    use crate::weighted_graph;
+
+    #[test]
+    fn acceptance_criteria_are_parsed() {
+        assert_eq!(Ok(Acceptance::Better), Acceptance::parse("better"));
+        assert_eq!(Ok(Acceptance::RandomWalk), Acceptance::parse("random-walk"));
+        assert_eq!(Ok(Acceptance::Restart { after: 50 }), Acceptance::parse("restart:50"));
+        assert!(Acceptance::parse("restart").is_err());
+        assert!(Acceptance::parse("better:3").is_err());
+    }
+
+    #[test]
+    fn search_finds_the_optimum_of_a_small_instance() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(12, 1));
+        let (_, optimum) = exact::dynamic_programming(&graph).unwrap();
+
+        for acceptance in ["better", "random-walk", "restart:20"] {
+            let args = ["tsp", "unused", "2", "500", "--seed", "1", "--acceptance", acceptance];
+            let mut config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            config.termination.target_length = Some(optimum + 1e-9);
+            let budget = Budget::from_config(&config);
+            let result = iterated_local_search(&config, &graph, &budget, &mut Silent);
+
+            assert!((result.best_length - optimum).abs() < 1e-9);
+        }
+    }
+}
@@ -1,134 +1,406 @@
 mod file_parser;
 pub mod config;
-mod weighted_graph;
-mod genotype;
+pub mod adaptive;
+pub mod annealing;
+pub mod ant_colony;
+pub mod selection;
+pub mod population;
+pub mod diversity;
+pub mod result;
+pub mod restart;
+pub mod termination;
+pub mod construction;
+pub mod bench;
+pub mod bound;
+pub mod checkpoint;
+pub mod local_search;
+pub mod observer;
+pub mod iterated_local_search;
+pub mod exact;
+pub mod island;
+pub mod schedule;
+pub mod solver;
+pub mod tabu;
+pub mod tuning;
+pub mod weighted_graph;
+pub mod generator;
+pub mod genotype;
 
 
 use rand::prelude::*;
 use rand::distributions::WeightedIndex;
-use rand::distributions::uniform::SampleUniform;
-use std::fs::{File, OpenOptions};
-use std::io;
-use std::io::prelude::*;
-use std::io::Write;
+use rand_chacha::ChaCha8Rng;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 use weighted_graph::*;
 use genotype::*;
 use config::*;
+use schedule::*;
+use adaptive::*;
+use selection::{probability_by_rank, Prepared};
+use population::Plan;
+use diversity::Diversity;
+use result::RunResult;
+use restart::StagnationDetector;
+use termination::StopReason;
+use construction::Heuristic;
+use solver::{Budget, Solver};
+use checkpoint::Checkpoint;
+use observer::{Event, Observer, PhaseChange, Printer, Signal, Statistics};
+use termination::TerminationCheck;
+
+/// Iterations of subgradient ascent used for the Held-Karp lower bound.
+const HELD_KARP_ITERATIONS: usize = 1000;
 
 /// Attempts to run the traveling salesperson problem, using the file named
 /// by the argument to populate the map.
-/// Returns an Error if the string does not represent a file, or if the file
-/// is not correctly formatted.
+/// Returns the best tour found along with statistics about the run, or an
+/// Error if the string does not represent a file, or if the file is not
+/// correctly formatted. Progress is printed to standard output.
 pub fn run(config: Config) 
--> Result<(), &'static str> {
-    let file_name = config.file_name;
-    let pop_size = config.pop_size;
-    let num_evals = config.num_evals;
-    
-    let mut rng = rand::thread_rng();
+-> Result<RunResult, &'static str> {
+    run_observed(config, &mut Printer)
+}
 
-    let point_vector = file_parser::parse_file(&file_name)?;
+/// As run, but reports progress to the observer instead of printing it.
+pub fn run_observed(config: Config, observer: &mut dyn Observer)
+-> Result<RunResult, &'static str> {
+    let point_vector = file_parser::parse_file(&config.file_name)?;
     let graph = WeightedGraph::from_points(point_vector);
+    config.validate_instance(&graph)?;
+
+    let budget = Budget::from_config(&config);
+    Ok(config.solve(&graph, &budget, observer))
+}
+
+/// Runs the genetic algorithm on the given graph, ignoring the file name in
+/// the config, which must have been validated against the graph with
+/// Config::validate_instance.
+pub fn evolve(config: &Config, graph: &WeightedGraph, budget: &Budget,
+              observer: &mut dyn Observer) -> RunResult {
+    evolve_on_island(config, graph, budget, observer, config.rng(), None)
+}
+
+/// Runs the genetic algorithm with the given random number generator,
+/// migrating members to and from other islands if it is one of several.
+fn evolve_on_island(config: &Config, graph: &WeightedGraph, budget: &Budget,
+                    observer: &mut dyn Observer, mut rng: ChaCha8Rng,
+                    island: Option<&island::Island>) -> RunResult {
+    let pop_size = config.pop_size;
+    let num_evals = budget.num_evals;
     
     let num_alleles = graph.num_vertices();
+    let resume = config.resume.as_ref();
 
     // INVARIANT: POPULATION MUST ALWAYS BE SORTED BY FITNESS AT ALL TIMES!!!
-    let mut pop_and_fitness = initial_population(
-        pop_size, num_alleles, &graph, &mut rng
-    );
-
-    let selection_dist = (0..pop_size)
-        .rev()
-        .map(|rank| probability_by_rank(2.0, rank, pop_size));
-    let parent_selection_dist = WeightedIndex::new(
-            selection_dist.clone().collect::<Vec<f64>>()
-        )
-        .unwrap();
+    let mut pop_and_fitness = match resume {
+        Some(checkpoint) => checkpoint.population.clone(),
+        None => initial_population(pop_size, num_alleles, &config.seeding, graph, &mut rng),
+    };
 
     let survivor_selection_dist = WeightedIndex::new(
-            selection_dist.rev().collect::<Vec<f64>>()
+            (0..pop_size)
+                .map(|rank| probability_by_rank(2.0, rank, pop_size))
+                .collect::<Vec<f64>>()
         )
         .unwrap();
-    for gen in 1..=num_evals {
-
-        let parent1 = &pop_and_fitness[parent_selection_dist.sample(&mut rng)];
-        let parent2 = &pop_and_fitness[parent_selection_dist.sample(&mut rng)];
-        let mut child1 = Genotype::edge_crossover(&parent1.0, &parent2.0, &mut rng);
-        
-        if gen > 1000 {
-            child1 = child1.inversion_mutation(&mut rng);
-            let child1_fitness = fitness(&graph, child1.data());
-            let child1 = (child1, child1_fitness);
-            replace_worst(&mut pop_and_fitness, child1);
-        } else {
-            child1 = child1.swap_mutation(&mut rng);
-            let child1_fitness = fitness(&graph, child1.data());
-            let child1 = (child1, child1_fitness);
-            replace_with_probability(
-                &mut pop_and_fitness, child1, &survivor_selection_dist, &mut rng
-            );
+    let parent_selection = Prepared::new(config.parent_selection.as_ref(), pop_size);
+
+    let mut schedule = ScheduleState::new(&config.schedule, pop_and_fitness[0].1);
+    let mut adaptive_operators = config.operator_selection.map(|strategy| (
+        OperatorSelector::new(&Crossover::ALL, strategy),
+        OperatorSelector::new(&Mutation::ALL, strategy),
+    ));
+    let mut stagnation = StagnationDetector::new(
+        config.stagnation_window, config.min_bond_distance, pop_and_fitness[0].1
+    );
+    // A resumed run keeps the lower bound it computed when it started.
+    let criteria = match resume {
+        Some(checkpoint) if config.compute_lower_bound => termination::Termination {
+            lower_bound: checkpoint.lower_bound,
+            ..budget.termination.clone()
+        },
+        _ => termination_criteria(config, budget, graph, pop_and_fitness[0].1, observer),
+    };
+    let mut termination = criteria.start(num_evals, pop_and_fitness[0].1);
+    let mut best = pop_and_fitness[0].clone();
+    // Each breeding thread has its own generator, drawn from the run's, so
+    // that a seeded run is reproducible for a given number of threads.
+    let mut breeders = (0..config.threads)
+        .map(|_| ChaCha8Rng::from_rng(&mut rng).unwrap())
+        .collect::<Vec<ChaCha8Rng>>();
+    let mut evals = 0;
+    if let Some(checkpoint) = resume {
+        evals = checkpoint.evals;
+        best = checkpoint.best.clone();
+        rng = checkpoint.rng.clone();
+        breeders = checkpoint.breeders.clone();
+        schedule.restore(checkpoint.schedule);
+        stagnation.restore(checkpoint.stagnation);
+        termination.restore(checkpoint.termination, checkpoint.elapsed);
+        if let (Some((crossovers, mutations)), Some((crossover_state, mutation_state))) =
+            (&mut adaptive_operators, &checkpoint.operators) {
+            crossovers.restore(crossover_state);
+            mutations.restore(mutation_state);
+        }
+    }
+    notify_new_best(observer, &mut termination, evals, &best);
+    let candidates = config.local_search.map(|_| graph.nearest_neighbors(config.candidates));
+    let local_search = config.local_search.zip(candidates.as_deref());
+    let mut last_checkpoint = evals;
+    let stop_reason: StopReason;
+    loop {
+        let stop = termination.check(evals, best.1);
+        // The state is saved after the check, which a resumed run repeats
+        // to the same effect, so that it carries on exactly.
+        if let Some(file_name) = &config.checkpoint {
+            let interval = config.checkpoint_interval;
+            if stop.is_some() || evals / interval > last_checkpoint / interval {
+                let checkpoint = Checkpoint {
+                    evals,
+                    elapsed: termination.elapsed(),
+                    lower_bound: criteria.lower_bound,
+                    best: best.clone(),
+                    population: pop_and_fitness.clone(),
+                    rng: rng.clone(),
+                    breeders: breeders.clone(),
+                    schedule: schedule.state(),
+                    stagnation: stagnation.state(),
+                    termination: termination.state(),
+                    operators: adaptive_operators.as_ref()
+                        .map(|(crossovers, mutations)| (crossovers.state(), mutations.state())),
+                };
+                let error = checkpoint.write(file_name).err();
+                notify(observer, &mut termination, Event::Checkpoint { evals, error });
+                last_checkpoint = evals;
+            }
+        }
+        if let Some(reason) = stop {
+            stop_reason = reason;
+            break;
         }
 
-        if gen % 10 == 0 {
-            println!("{} {}", gen, pop_and_fitness[0].1);
+        let phase = *schedule.phase();
+        let num_offspring = config.population_model
+            .num_offspring(pop_size)
+            .min(num_evals - evals);
+
+        // The generation's parents are chosen at once and its operators in
+        // turn, then the children are bred and evaluated in parallel.
+        let parents = parent_selection.select(&pop_and_fitness, 2 * num_offspring, &mut rng);
+        let plans = parents
+            .chunks(2)
+            .map(|parents| {
+                let (crossover, mutation) = match &mut adaptive_operators {
+                    None => (phase.crossover, phase.mutation),
+                    Some((crossovers, mutations)) =>
+                        (crossovers.select(&mut rng), mutations.select(&mut rng)),
+                };
+                Plan { parents: (parents[0], parents[1]), crossover, mutation }
+            })
+            .collect::<Vec<Plan>>();
+        let offspring = population::breed(
+            &plans, &pop_and_fitness, graph, local_search, &mut breeders
+        );
+
+        if let Some((crossovers, mutations)) = &mut adaptive_operators {
+            for (plan, child) in plans.iter().zip(offspring.iter()) {
+                let (parent1, parent2) = plan.parents;
+                let parent_fitness = pop_and_fitness[parent1].1.min(pop_and_fitness[parent2].1);
+                // Credit is the relative improvement over the better parent.
+                let credit = ((parent_fitness - child.fitness) / parent_fitness)
+                    .max(0.0);
+                crossovers.reward(plan.crossover, credit);
+                mutations.reward(plan.mutation, credit);
+            }
+        }
+
+        config.population_model.advance(
+            &mut pop_and_fitness, offspring, phase.survivor,
+            &survivor_selection_dist, config.reject_duplicates, &mut rng
+        );
+        let prev_evals = evals;
+        evals += num_offspring;
+        if schedule.update(evals, termination.progress(evals), pop_and_fitness[0].1) {
+            let change = PhaseChange::Schedule {
+                index: schedule.phase_idx(),
+                phase: schedule.phase(),
+            };
+            notify(observer, &mut termination, Event::PhaseChange { evals, change });
+        }
+
+        let trigger = stagnation.check(evals, &pop_and_fitness);
+        if let Some(trigger) = trigger.filter(|_| evals < num_evals) {
+            let response = config.stagnation_response;
+            evals += response.apply(&mut pop_and_fitness, graph, num_evals - evals, &mut rng);
+            stagnation.reset(evals, pop_and_fitness[0].1);
+            let change = PhaseChange::Stagnation {
+                trigger: trigger.name(),
+                response: response.name(),
+            };
+            notify(observer, &mut termination, Event::PhaseChange { evals, change });
+        }
+        if let Some(island) = island {
+            let interval = island.interval();
+            if evals / interval > prev_evals / interval {
+                island.migrate(&mut pop_and_fitness, &mut rng);
+            }
+        }
+        if pop_and_fitness[0].1 < best.1 {
+            best = pop_and_fitness[0].clone();
+            notify_new_best(observer, &mut termination, evals, &best);
+        }
+
+        if evals / 10 > prev_evals / 10 {
+            let best = pop_and_fitness[0].1;
+            notify(observer, &mut termination, Event::Progress { evals, best });
+        }
+        let interval = config.diversity_interval;
+        if interval > 0 && evals / interval > prev_evals / interval {
+            let diversity = Diversity::measure(&pop_and_fitness);
+            let statistics = Statistics::Population(&diversity);
+            notify(observer, &mut termination, Event::Statistics { evals, statistics });
         }
     }
 
-    Ok(())
-}
+    if let Some((crossovers, mutations)) = &adaptive_operators {
+        notify_operator_stats(observer, crossovers, Crossover::name);
+        notify_operator_stats(observer, mutations, Mutation::name);
+    }
 
-fn try_open_file(file_name: &str) -> Result<File, &'static str> {
-    match OpenOptions::new().write(true).open("stats.txt") {
-        Err(_) => Err("could not open output file"),
-        Ok(file) => Ok(file),
+    notify_stop(observer, evals, stop_reason, best.1, criteria.lower_bound);
+
+    RunResult {
+        best_tour: best.0.data().to_vec(),
+        best_length: best.1,
+        evaluations: evals,
+        elapsed: termination.elapsed(),
+        stop_reason,
+        diversity: Diversity::measure(&pop_and_fitness),
+        lower_bound: criteria.lower_bound,
     }
 }
 
-fn replace_with_probability<X>(pop_and_fitness: &mut Vec<(Genotype, f64)>,
-                            child: (Genotype, f64), 
-                            selection_dist: &WeightedIndex<X>, 
-                            rng: &mut ThreadRng,
-                            ) 
-where X: SampleUniform + PartialOrd 
-{
-    pop_and_fitness.remove(selection_dist.sample(rng));
-    let mut idx = 0;
-    for x in pop_and_fitness.iter() {
-        if child.1 > x.1 {
-            idx += 1;
+/// Returns the budget's stop conditions, computing the Held-Karp lower bound
+/// if the config asks for it, using the initial best length as its upper
+/// bound. If the observer asks to stop on seeing the bound, the run is
+/// cancelled before it starts.
+fn termination_criteria(config: &Config, budget: &Budget, graph: &WeightedGraph,
+                        initial_best: f64, observer: &mut dyn Observer)
+-> termination::Termination {
+    let mut criteria = budget.termination.clone();
+    if config.compute_lower_bound {
+        let bound = bound::held_karp_bound(graph, Some(initial_best), HELD_KARP_ITERATIONS);
+        if observer.notify(&Event::Bound { value: bound }) == Signal::Stop {
+            criteria.cancel = Some(Arc::new(AtomicBool::new(true)));
         }
+        criteria.lower_bound = Some(bound);
     }
-    pop_and_fitness.insert(idx, child);
+    criteria
 }
 
-fn replace_worst(pop_and_fitness: &mut Vec<(Genotype, f64)>, 
-                 child: (Genotype, f64)) {
-    let mut idx = 0;
-    for x in pop_and_fitness.iter() {
-        if child.1 > x.1 {
-            idx += 1;
-        }
+/// Reports the event to the observer, and stops the run at its next check
+/// if the observer asks to.
+fn notify(observer: &mut dyn Observer, termination: &mut TerminationCheck, event: Event) {
+    if observer.notify(&event) == Signal::Stop {
+        termination.stop();
     }
-    pop_and_fitness.insert(idx, child);
-    pop_and_fitness.pop();
 }
 
-fn initial_population(pop_size: usize, num_alleles: usize, 
-                      graph: &WeightedGraph, rng: &mut ThreadRng) 
+fn notify_new_best(observer: &mut dyn Observer, termination: &mut TerminationCheck,
+                   evals: usize, best: &(Genotype, f64)) {
+    let event = Event::NewBest { evals, tour: best.0.data(), length: best.1 };
+    notify(observer, termination, event);
+}
+
+/// Reports why the run stopped, with the lower bound if there is one.
+fn notify_stop(observer: &mut dyn Observer, evals: usize, reason: StopReason, best: f64,
+               lower_bound: Option<f64>) {
+    observer.notify(&Event::Stop { evals, reason, best, lower_bound });
+}
+
+/// Reports each operator's number of uses, mean credit and final selection
+/// probability.
+fn notify_operator_stats<T>(observer: &mut dyn Observer, selector: &OperatorSelector<T>,
+                            name: fn(T) -> &'static str)
+where T: Copy + PartialEq
+{
+    for (op, uses, credit, probability) in selector.stats() {
+        observer.notify(&Event::Operator { name: name(op), uses, credit, probability });
+    }
+}
+
+/// Builds a tour for the file's cities with a single construction heuristic,
+/// returning the tour and its length.
+pub fn construct(file_name: &str, heuristic: Heuristic)
+-> Result<(Vec<usize>, f64), &'static str> {
+    let mut rng = ChaCha8Rng::from_entropy();
+    let graph = WeightedGraph::from_points(file_parser::parse_file(file_name)?);
+    let tour = heuristic.construct(&graph, &mut rng);
+    let length = fitness(&graph, tour.data());
+    Ok((tour.data().to_vec(), length))
+}
+
+/// Finds an optimal tour for the file's cities with the Held-Karp dynamic
+/// program, returning the tour and its length. Only practical for up to
+/// exact::MAX_DYNAMIC_PROGRAMMING_CITIES cities.
+pub fn solve_exact(file_name: &str)
+-> Result<(Vec<usize>, f64), &'static str> {
+    let graph = WeightedGraph::from_points(file_parser::parse_file(file_name)?);
+    exact::dynamic_programming(&graph)
+}
+
+/// Runs the configured algorithm, then proves its best tour optimal or finds
+/// a better one by branch and bound, stopping at the time limit if there is
+/// one.
+pub fn solve_branch_and_bound(config: Config, time_limit: Option<Duration>)
+-> Result<exact::BranchAndBoundResult, &'static str> {
+    let point_vector = file_parser::parse_file(&config.file_name)?;
+    let graph = WeightedGraph::from_points(point_vector);
+    config.validate_instance(&graph)?;
+
+    let budget = Budget::from_config(&config);
+    let result = config.solve(&graph, &budget, &mut Printer);
+    Ok(exact::branch_and_bound(
+        &graph, &result.best_tour, time_limit, usize::MAX, &mut observer::Silent
+    ))
+}
+
+/// Builds a population sorted by fitness. Each heuristic in seeding builds
+/// its fraction of the population, and the rest is random. Copies of tours
+/// from deterministic heuristics after the first get an inversion mutation,
+/// so they are not all identical.
+fn initial_population(pop_size: usize, num_alleles: usize,
+                      seeding: &[(Heuristic, f64)],
+                      graph: &WeightedGraph, rng: &mut ChaCha8Rng) 
 -> Vec<(Genotype, f64)> {
-    let population = (0..pop_size)
-        .map(|_| Genotype::random(num_alleles, rng))
-        .collect::<Vec<Genotype>>();
+    let mut population = Vec::with_capacity(pop_size);
+    for &(heuristic, fraction) in seeding {
+        let count = (fraction * pop_size as f64).round() as usize;
+        let count = count.min(pop_size - population.len());
+        for i in 0..count {
+            let genotype = heuristic.construct(graph, rng);
+            if heuristic.is_deterministic() && i > 0 {
+                population.push(genotype.inversion_mutation(rng));
+            } else {
+                population.push(genotype);
+            }
+        }
+    }
+    while population.len() < pop_size {
+        population.push(Genotype::random(num_alleles, rng));
+    }
 
     let fitness = population
         .iter()
-        .map(|genotype| fitness(&graph, &genotype.data()))
+        .map(|genotype| fitness(graph, genotype.data()))
         .collect::<Vec<f64>>();
 
     let mut pop_fitness = population
         .into_iter()
-        .zip(fitness.into_iter())
+        .zip(fitness)
         .collect::<Vec<(Genotype, f64)>>();
     pop_fitness.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
 
@@ -143,7 +415,7 @@ fn fitness(environ: &WeightedGraph, individual: &[usize]) -> f64 {
 
     let mut current_vertex = individual[0];
     individual
-        .into_iter()
+        .iter()
         .fold(final_edge, |acc, &x| {
             let dist = environ.weight_between(current_vertex, x);
             current_vertex = x;
@@ -151,13 +423,6 @@ fn fitness(environ: &WeightedGraph, individual: &[usize]) -> f64 {
         })
 }
 
-fn probability_by_rank(s: f64, rank: usize, pop_size: usize) -> f64 {
-    let rank = rank as f64;
-    let pop_size = pop_size as f64;
-    (2.0 - s) / pop_size + 
-        rank * (2.0 * s - 2.0) / (pop_size.powf(2.0) - pop_size)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,6 +437,112 @@ mod tests {
         ];
         let graph = WeightedGraph::from_points(point_vec);
 
-        assert_eq!(20.0, fitness(graph, &genotype));
+        assert_eq!(20.0, fitness(&graph, &genotype));
+    }
+
+    #[test]
+    fn evolution_reaches_the_optimum_of_a_small_instance() {
+        let points = (0..9)
+            .map(|i| ((i * 7 % 11) as f64, (i * i % 13) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let (_, optimum) = exact::dynamic_programming(&graph).unwrap();
+
+        let args = ["tsp", "unused", "30", "20000"].map(String::from);
+        let mut config = Config::from_args(args.into_iter()).unwrap();
+        config.termination.target_length = Some(optimum + 1e-9);
+        let budget = Budget::from_config(&config);
+        let result = evolve(&config, &graph, &budget, &mut observer::Silent);
+
+        assert!((result.best_length - optimum).abs() < 1e-9);
+    }
+
+    #[test]
+    fn seeded_parallel_runs_are_reproducible() {
+        let points = (0..30)
+            .map(|i| ((i * 17 % 31) as f64, (i * i % 37) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+
+        let run = |model: &str, threads: &str| {
+            let args = [
+                "tsp", "unused", "20", "2000", "--seed", "7", "--threads", threads,
+                "--population_model", model, "--local_search", "2-opt",
+                "--operator_selection", "bandit",
+            ];
+            let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            let budget = Budget::from_config(&config);
+            evolve(&config, &graph, &budget, &mut observer::Silent)
+        };
+
+        for model in ["generational:2", "plus:30"] {
+            let first = run(model, "3");
+            let second = run(model, "3");
+            assert_eq!(first.best_tour, second.best_tour);
+            assert_eq!(first.best_length, second.best_length);
+            assert_eq!(2000, first.evaluations);
+            assert!((fitness(&graph, &first.best_tour) - first.best_length).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn resumed_runs_carry_on_exactly() {
+        let points = (0..30)
+            .map(|i| ((i * 17 % 31) as f64, (i * i % 37) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let file_name = std::env::temp_dir()
+            .join(format!("tsp-resume-test-{}", std::process::id()));
+        let file_name = file_name.to_str().unwrap();
+
+        let args = |extra: &[&str]| {
+            let mut args = vec![
+                "tsp", "unused", "20", "3000", "--seed", "11", "--threads", "2",
+                "--population_model", "generational:2", "--operator_selection", "pursuit",
+                "--stagnation_window", "400", "--stagnation_response", "hypermutation:3",
+            ];
+            args.extend_from_slice(extra);
+            args.into_iter().map(String::from).collect::<Vec<String>>()
+        };
+        let config = |extra: &[&str]| Config::from_args(args(extra).into_iter()).unwrap();
+        let run = |extra: &[&str], stop_at: usize| {
+            let config = config(extra);
+            config.validate_instance(&graph).unwrap();
+            let budget = Budget::from_config(&config);
+            let mut new_bests = Vec::new();
+            let result = evolve(&config, &graph, &budget, &mut |event: &Event| {
+                match *event {
+                    Event::NewBest { evals, length, .. } => new_bests.push((evals, length)),
+                    Event::Progress { evals, .. } if evals >= stop_at => return Signal::Stop,
+                    _ => (),
+                }
+                Signal::Continue
+            });
+            (result, new_bests)
+        };
+
+        let (whole, whole_bests) = run(&[], usize::MAX);
+        let (first, _) = run(&["--checkpoint", file_name], 1500);
+        let (second, second_bests) = run(&["--resume", file_name], usize::MAX);
+        let other = WeightedGraph::from_points(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+        assert!(config(&["--resume", file_name]).validate_instance(&other).is_err());
+        let mut short_member = config(&["--resume", file_name]);
+        let checkpoint = short_member.resume.as_mut().unwrap();
+        checkpoint.population[1].0 = Genotype::from_data((0..29).collect());
+        assert!(short_member.validate_instance(&graph).is_err());
+        let mut checkpoint = checkpoint::Checkpoint::read(file_name).unwrap();
+        checkpoint.schedule.0 = config(&[]).schedule.phases().len();
+        checkpoint.write(file_name).unwrap();
+        let resumed = Config::from_args(args(&["--resume", file_name]).into_iter());
+        assert_eq!(Some("checkpoint does not match the schedule"), resumed.err());
+        std::fs::remove_file(file_name).unwrap();
+
+        assert_eq!(StopReason::Cancelled, first.stop_reason);
+        assert_eq!(whole.best_tour, second.best_tour);
+        assert_eq!(whole.best_length, second.best_length);
+        assert_eq!(whole.evaluations, second.evaluations);
+        // Apart from the best tour reported on resuming, the resumed run
+        // finds the same tours at the same times.
+        assert!(whole_bests.ends_with(&second_bests[1..]));
     }
 }
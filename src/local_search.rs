@@ -0,0 +1,299 @@
+use std::collections::VecDeque;
+
+use crate::weighted_graph::WeightedGraph;
+
+/// Length of the candidate lists of nearest neighbors unless configured
+/// otherwise.
+pub const DEFAULT_CANDIDATES: usize = 15;
+
+/// Improvements smaller than this are ignored, so that rounding errors
+/// cannot make a search cycle.
+const EPSILON: f64 = 1e-9;
+
+/// A local search that improves a tour until no move of its kind shortens
+/// it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LocalSearch {
+    TwoOpt,
+    OrOpt,
+    /// Alternates 2-opt and Or-opt until neither improves the tour.
+    TwoOptOrOpt,
+}
+
+impl LocalSearch {
+    pub const ALL: [Self; 3] = [Self::TwoOpt, Self::OrOpt, Self::TwoOptOrOpt];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|search| search.name() == name.trim())
+            .ok_or("unknown local search")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TwoOpt => "2-opt",
+            Self::OrOpt => "or-opt",
+            Self::TwoOptOrOpt => "2-opt+or-opt",
+        }
+    }
+
+    /// Improves the tour in place, considering only moves that join a city
+    /// to one of its candidates. Returns the reduction in tour length.
+    pub fn apply(self, tour: &mut [usize], graph: &WeightedGraph,
+                 candidates: &[Vec<usize>]) -> f64 {
+        match self {
+            Self::TwoOpt => two_opt(tour, graph, candidates),
+            Self::OrOpt => or_opt(tour, graph, candidates),
+            Self::TwoOptOrOpt => {
+                let mut improvement = two_opt(tour, graph, candidates);
+                loop {
+                    let step = or_opt(tour, graph, candidates);
+                    if step == 0.0 {
+                        break;
+                    }
+                    improvement += step + two_opt(tour, graph, candidates);
+                }
+                improvement
+            }
+        }
+    }
+}
+
+/// Applies improving 2-opt moves, each of which replaces two edges of the
+/// tour with two shorter ones by reversing the path between them, until
+/// there are none left. A move is only tried if it joins a city to one of
+/// its candidates that is closer than its current neighbour, and cities
+/// whose edges have not changed since they last failed to improve are not
+/// looked at again. Returns the reduction in tour length.
+pub fn two_opt(tour: &mut [usize], graph: &WeightedGraph,
+               candidates: &[Vec<usize>]) -> f64 {
+    let num_vertices = tour.len();
+    if num_vertices < 5 {
+        return 0.0;
+    }
+    let weight = |v: usize, u: usize| graph.weight_between(v, u);
+    let mut position = vec![0; num_vertices];
+    for (i, &city) in tour.iter().enumerate() {
+        position[city] = i;
+    }
+
+    let mut queue = tour.iter().copied().collect::<VecDeque<usize>>();
+    let mut queued = vec![true; num_vertices];
+    let mut improvement = 0.0;
+
+    while let Some(a) = queue.pop_front() {
+        queued[a] = false;
+        'directions: for forward in [true, false] {
+            let step = |city: usize, position: &[usize], tour: &[usize]| match forward {
+                true => tour[(position[city] + 1) % num_vertices],
+                false => tour[(position[city] + num_vertices - 1) % num_vertices],
+            };
+            let b = step(a, &position, tour);
+            let ab = weight(a, b);
+
+            for &c in candidates[a].iter() {
+                let ac = weight(a, c);
+                if ac >= ab {
+                    break;
+                }
+                let d = step(c, &position, tour);
+                if c == b || d == a {
+                    continue;
+                }
+                let delta = ac + weight(b, d) - ab - weight(c, d);
+                if delta < -EPSILON {
+                    // Going forward the tour reads a b .. c d, and reversing
+                    // b .. c gives a c .. b d; going backward it reads
+                    // d c .. b a, and reversing a .. d gives b d .. c a.
+                    let (from, to) = match forward {
+                        true => (position[b], position[c]),
+                        false => (position[a], position[d]),
+                    };
+                    reverse(tour, &mut position, from, to);
+                    improvement -= delta;
+                    for city in [a, b, c, d] {
+                        if !queued[city] {
+                            queued[city] = true;
+                            queue.push_back(city);
+                        }
+                    }
+                    break 'directions;
+                }
+            }
+        }
+    }
+    improvement
+}
+
+/// Longest segment of consecutive cities Or-opt moves.
+const OR_OPT_SEGMENT: usize = 3;
+
+/// Applies improving Or-opt moves, each of which moves a segment of up to
+/// three consecutive cities, possibly reversed, to between two other
+/// adjacent cities, until there are none left. A move is only tried if it
+/// joins an end of the segment to one of its candidates. Returns the
+/// reduction in tour length.
+pub fn or_opt(tour: &mut [usize], graph: &WeightedGraph,
+              candidates: &[Vec<usize>]) -> f64 {
+    let num_vertices = tour.len();
+    if num_vertices < OR_OPT_SEGMENT + 3 {
+        return 0.0;
+    }
+    let weight = |v: usize, u: usize| graph.weight_between(v, u);
+    let mut position = vec![0; num_vertices];
+    let mut improvement = 0.0;
+
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for (i, &city) in tour.iter().enumerate() {
+            position[city] = i;
+        }
+
+        'segments: for start in 0..num_vertices {
+            for len in 1..=OR_OPT_SEGMENT {
+                let at = |pos: usize| tour[pos % num_vertices];
+                let (first, last) = (tour[start], at(start + len - 1));
+                let (prev, next) = (at(start + num_vertices - 1), at(start + len));
+                let in_segment = |city: usize| {
+                    (position[city] + num_vertices - start) % num_vertices < len
+                };
+                let removal_gain = weight(prev, first) + weight(last, next) - weight(prev, next);
+
+                // Join c to one end of the segment and its neighbour d to the
+                // other, where d is on either side of c.
+                for (end, other) in [(first, last), (last, first)] {
+                    for &c in candidates[end].iter() {
+                        let joined = weight(c, end);
+                        if joined >= removal_gain {
+                            break;
+                        }
+                        if in_segment(c) {
+                            continue;
+                        }
+                        for d in [at(position[c] + 1), at(position[c] + num_vertices - 1)] {
+                            if in_segment(d) {
+                                continue;
+                            }
+                            let delta = joined + weight(other, d) - weight(c, d) - removal_gain;
+                            if delta < -EPSILON {
+                                move_segment(tour, start, len, c, d, end);
+                                improvement -= delta;
+                                improved = true;
+                                break 'segments;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    improvement
+}
+
+/// Moves the segment of `len` cities starting at position `start` to
+/// between the adjacent cities c and d, with `end` next to c.
+fn move_segment(tour: &mut [usize], start: usize, len: usize, c: usize, d: usize, end: usize) {
+    let num_vertices = tour.len();
+    let mut segment = (0..len)
+        .map(|i| tour[(start + i) % num_vertices])
+        .collect::<Vec<usize>>();
+    let mut rest = (len..num_vertices)
+        .map(|i| tour[(start + i) % num_vertices])
+        .collect::<Vec<usize>>();
+
+    // Orient the segment to read from the end joined to c.
+    if segment[0] != end {
+        segment.reverse();
+    }
+    let c_idx = rest.iter().position(|&city| city == c).unwrap();
+    if rest[(c_idx + 1) % rest.len()] == d {
+        rest.splice(c_idx + 1..c_idx + 1, segment);
+    } else {
+        segment.reverse();
+        rest.splice(c_idx..c_idx, segment);
+    }
+    tour.copy_from_slice(&rest);
+}
+
+/// Reverses the part of the tour running forward from position `from` to
+/// position `to` inclusive, wrapping around the end. The rest of the tour is
+/// reversed instead if it is shorter, which gives the same tour.
+fn reverse(tour: &mut [usize], position: &mut [usize], from: usize, to: usize) {
+    let num_vertices = tour.len();
+    let len = (to + num_vertices - from) % num_vertices + 1;
+    let (mut i, mut j, len) = match len * 2 > num_vertices {
+        true => ((to + 1) % num_vertices, (from + num_vertices - 1) % num_vertices,
+                 num_vertices - len),
+        false => (from, to, len),
+    };
+    for _ in 0..len / 2 {
+        tour.swap(i, j);
+        position[tour[i]] = i;
+        position[tour[j]] = j;
+        i = (i + 1) % num_vertices;
+        j = (j + num_vertices - 1) % num_vertices;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use rand::prelude::*;
+
+    #[test]
+    fn two_opt_leaves_no_improving_move() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let points = (0..40)
+            .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let candidates = graph.nearest_neighbors(39);
+
+        let mut tour = (0..40).collect::<Vec<usize>>();
+        tour.shuffle(&mut rng);
+        let before = crate::fitness(&graph, &tour);
+        let improvement = two_opt(&mut tour, &graph, &candidates);
+        let after = crate::fitness(&graph, &tour);
+        assert!((before - after - improvement).abs() < 1e-6);
+
+        let mut sorted = tour.clone();
+        sorted.sort();
+        assert_eq!((0..40).collect::<Vec<usize>>(), sorted);
+
+        let weight = |i: usize, j: usize| graph.weight_between(tour[i % 40], tour[j % 40]);
+        for i in 0..40 {
+            for j in i + 2..40 {
+                let delta = weight(i, j) + weight(i + 1, j + 1)
+                    - weight(i, i + 1) - weight(j, j + 1);
+                assert!(delta > -1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn or_opt_improves_without_breaking_the_tour() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let points = (0..40)
+            .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let candidates = graph.nearest_neighbors(10);
+
+        for search in [LocalSearch::OrOpt, LocalSearch::TwoOptOrOpt] {
+            let mut tour = (0..40).collect::<Vec<usize>>();
+            tour.shuffle(&mut rng);
+            let before = crate::fitness(&graph, &tour);
+            let improvement = search.apply(&mut tour, &graph, &candidates);
+            let after = crate::fitness(&graph, &tour);
+            assert!(improvement > 0.0);
+            assert!((before - after - improvement).abs() < 1e-6);
+
+            let mut sorted = tour.clone();
+            sorted.sort();
+            assert_eq!((0..40).collect::<Vec<usize>>(), sorted);
+        }
+    }
+}
@@ -1,24 +1,270 @@
+use tsp::bench::{self, Setup};
 use tsp::config::Config;
-use std::{env, process};
+use tsp::construction::Heuristic;
+use tsp::generator::{self, Kind};
+use tsp::tuning::{self, Space};
+use std::{env, fs, process};
+use std::path::Path;
+use std::time::Duration;
 
-/// usage: cargo run <filename>
+/// usage: cargo run <filename> <pop_size> <num_evals> [--key value ...]
+///        cargo run construct <filename> <heuristic>
+///        cargo run exact <filename>
+///        cargo run branch-and-bound <seconds> <filename> <pop_size> <num_evals> [...]
+///        cargo run bench <directory> <runs> <pop_size> <num_evals> [...]
+///        cargo run tune <directory> <candidates> <num_evals> [...]
+///        cargo run generate <kind> <cities> <seed> <filename>
 /// 
-/// Entry point to the program. Accepts one command line argument representing
-/// the name of the file containing the coordinates of cities to be traversed.
+/// Entry point to the program. Accepts command line arguments naming the file
+/// containing the coordinates of cities to be traversed, the population size
+/// and the number of evaluations, followed by any options. Alternatively,
+/// the `construct` subcommand builds a single tour with a heuristic, and the
+/// `exact` subcommand solves a small instance to optimality, as does
+/// `branch-and-bound` for larger ones within a time limit. The `bench`
+/// subcommand summarises repeated runs over a directory of instances, and
+/// `tune` searches for the GA parameters that do best on them. The
+/// `generate` subcommand writes a random instance.
 /// Returns an error if the wrong number of command line arguments is provided
 /// or if the file cannot be parsed according to the required format.
 fn main() -> Result<(), &'static str> {
-    let config = Config::from_args(env::args());
-    if let Err(_) = config {
-        eprintln!("usage: cargo run <file> <pop_size> <num_evals>");
+    let args = env::args().collect::<Vec<String>>();
+    let result = match args.get(1).map(String::as_str) {
+        Some("construct") => construct(&args[2..]),
+        Some("exact") => exact(&args[2..]),
+        Some("branch-and-bound") => branch_and_bound(&args[2..]),
+        Some("bench") => bench(&args[2..]),
+        Some("tune") => tune(&args[2..]),
+        Some("generate") => generate(&args[2..]),
+        _ => run(args),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error while processing: {}", err);
+        process::exit(1);
+    };
+    Ok(())
+}
+
+fn run(args: Vec<String>) -> Result<(), &'static str> {
+    let config = Config::from_args(args.into_iter());
+    if let Err(err) = config {
+        eprintln!("{}", err);
+        eprintln!(
+            "usage: cargo run <file> <pop_size> <num_evals> [--key value ...]"
+        );
         process::exit(1);
     }
 
-    let result = tsp::run(config.unwrap());
+    tsp::run(config.unwrap())?;
+    Ok(())
+}
 
-    if let Err(err) = result {
-        eprintln!("error while processing: {}", err);
+fn construct(args: &[String]) -> Result<(), &'static str> {
+    if args.len() != 2 {
+        eprintln!("usage: cargo run construct <file> \
+                   <nn|greedy|cheapest|farthest|hilbert|christofides|christofides-exact>");
         process::exit(1);
+    }
+
+    let heuristic = Heuristic::parse(&args[1])?;
+    let (tour, length) = tsp::construct(&args[0], heuristic)?;
+    println!("{}", length);
+    println!("{:?}", tour);
+    Ok(())
+}
+
+fn exact(args: &[String]) -> Result<(), &'static str> {
+    if args.len() != 1 {
+        eprintln!("usage: cargo run exact <file>");
+        process::exit(1);
+    }
+
+    let (tour, length) = tsp::solve_exact(&args[0])?;
+    println!("{}", length);
+    println!("{:?}", tour);
+    Ok(())
+}
+
+fn branch_and_bound(args: &[String]) -> Result<(), &'static str> {
+    let usage = "usage: cargo run branch-and-bound <seconds> <file> <pop_size> <num_evals> [--key value ...]";
+    let seconds = args.first().map(|seconds| seconds.parse::<f64>());
+    let seconds = match seconds {
+        Some(Ok(seconds)) if seconds > 0.0 => seconds,
+        _ => {
+            eprintln!("{}", usage);
+            process::exit(1);
+        }
+    };
+    let config_args = std::iter::once(String::from("tsp")).chain(args[1..].iter().cloned());
+    let config = match Config::from_args(config_args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("{}", usage);
+            process::exit(1);
+        }
+    };
+
+    let result = tsp::solve_branch_and_bound(config, Some(Duration::from_secs_f64(seconds)))?;
+    println!("nodes {}", result.nodes);
+    println!("bound {}", result.lower_bound);
+    match result.optimal {
+        true => println!("optimal"),
+        false => println!("gap {:.4}", tsp::termination::gap(result.best_length, result.lower_bound)),
+    }
+    println!("{}", result.best_length);
+    println!("{:?}", result.best_tour);
+    Ok(())
+}
+
+fn bench(args: &[String]) -> Result<(), &'static str> {
+    let usage = "usage: cargo run bench <directory> <runs> <pop_size> <num_evals> [--key value ...]";
+    let runs = args.get(1).and_then(|runs| runs.parse::<usize>().ok());
+    let (dir, runs) = match (args.first(), runs) {
+        (Some(dir), Some(runs)) if runs > 0 && args.len() >= 4 => (dir, runs),
+        _ => {
+            eprintln!("{}", usage);
+            process::exit(1);
+        }
+    };
+
+    // The bench options are taken out, and the rest shared by every setup.
+    let mut solutions = None;
+    let mut config_files = Vec::new();
+    let mut target_gap = 1.0;
+    let mut output = String::from("bench");
+    let mut shared = args[2..4].to_vec();
+    for option in args[4..].chunks(2) {
+        let (key, value) = match option {
+            [key, value] => (key.as_str(), value),
+            _ => return Err("option provided without a value"),
+        };
+        match key {
+            "--solutions" => solutions = Some(bench::read_solutions(value)?),
+            "--configs" => config_files = value.split(',').map(String::from).collect(),
+            "--target_gap" => {
+                target_gap = value.parse::<f64>()
+                    .map_err(|_| "could not parse target gap")?;
+            }
+            "--output" => output = value.clone(),
+            _ => shared.extend_from_slice(option),
+        }
+    }
+
+    let setups = match config_files.is_empty() {
+        true => vec![Setup { name: String::from("default"), args: shared }],
+        false => config_files
+            .iter()
+            .map(|file| {
+                let name = Path::new(file).file_stem().unwrap_or_default();
+                let config = [String::from("--config"), file.clone()];
+                Setup {
+                    name: name.to_string_lossy().into_owned(),
+                    args: shared.iter().cloned().chain(config).collect(),
+                }
+            })
+            .collect(),
     };
+    let instances = bench::read_instances(dir)?;
+    if instances.is_empty() {
+        return Err("no instances found in directory");
+    }
+
+    let mut summaries = Vec::new();
+    for instance in instances.iter() {
+        let optimum = solutions.as_ref()
+            .and_then(|solutions| solutions.get(&instance.name).copied());
+        for setup in setups.iter() {
+            let mut report = |run, result: &tsp::result::RunResult| println!(
+                "run {} {} {} {} {} {:.3}",
+                instance.name, setup.name, run, result.best_length, result.evaluations,
+                result.elapsed.as_secs_f64()
+            );
+            summaries.push(bench::bench(instance, setup, runs, optimum, target_gap, &mut report)?);
+        }
+    }
+
+    let markdown = bench::markdown(&summaries);
+    fs::write(format!("{}.csv", output), bench::csv(&summaries))
+        .map_err(|_| "error trying to write CSV file")?;
+    fs::write(format!("{}.md", output), &markdown)
+        .map_err(|_| "error trying to write Markdown file")?;
+    print!("{}", markdown);
+    Ok(())
+}
+
+fn tune(args: &[String]) -> Result<(), &'static str> {
+    let usage = "usage: cargo run tune <directory> <candidates> <num_evals> [--key value ...]";
+    let candidates = args.get(1).and_then(|candidates| candidates.parse::<usize>().ok());
+    let num_evals = args.get(2).and_then(|num_evals| num_evals.parse::<usize>().ok());
+    let (dir, candidates, num_evals) = match (args.first(), candidates, num_evals) {
+        (Some(dir), Some(candidates), Some(num_evals)) if candidates > 0 && num_evals > 0 =>
+            (dir, candidates, num_evals),
+        _ => {
+            eprintln!("{}", usage);
+            process::exit(1);
+        }
+    };
+
+    // The tuning options are taken out, and the rest shared by every run.
+    let mut space = Space::default();
+    let mut seed = None;
+    let mut output = String::from("tuned.cfg");
+    let mut shared = Vec::new();
+    for option in args[3..].chunks(2) {
+        let (key, value) = match option {
+            [key, value] => (key.as_str(), value),
+            _ => return Err("option provided without a value"),
+        };
+        match key {
+            "--pop_sizes" => space.pop_size = Space::parse_range(value)?,
+            "--pressures" => space.pressure = Space::parse_range(value)?,
+            "--switch_points" => space.switch_point = Space::parse_range(value)?,
+            "--seed" => seed = Some(value.parse::<u64>().map_err(|_| "could not parse seed")?),
+            "--output" => output = value.clone(),
+            _ => shared.extend_from_slice(option),
+        }
+    }
+    let instances = bench::read_instances(dir)?;
+    if instances.is_empty() {
+        return Err("no instances found in directory");
+    }
+
+    let tuned = tuning::tune(&instances, num_evals, &shared, &space, candidates, seed,
+                             &mut |round, tasks, survivors| {
+        let (best, score) = survivors[0];
+        let options = best.options().map(|(key, value)| format!("{} {}", key, value));
+        println!(
+            "round {} tasks {} survivors {} best {:.6} {}",
+            round, tasks, survivors.len(), score, options.join(" ")
+        );
+    })?;
+    fs::write(&output, tuned.best.config_file())
+        .map_err(|_| "error trying to write config file")?;
+    println!("runs {}", tuned.runs);
+    print!("{}", tuned.best.config_file());
+    Ok(())
+}
+
+fn generate(args: &[String]) -> Result<(), &'static str> {
+    if args.len() != 4 {
+        eprintln!("usage: cargo run generate <uniform|clustered|grid|circle|line> <cities> <seed> <file>");
+        process::exit(1);
+    }
+
+    let kind = Kind::parse(&args[0])?;
+    let num_cities = args[1].parse::<usize>()
+        .ok()
+        .filter(|&num_cities| num_cities > 0)
+        .ok_or("cities must be a positive integer")?;
+    let seed = args[2].parse::<u64>()
+        .map_err(|_| "could not parse seed")?;
+    let points = generator::generate(kind, num_cities, seed);
+    generator::write_instance(&args[3], &points)?;
+    // Printed as a line of a solutions file for the bench subcommand.
+    if let Some(optimum) = generator::known_optimum(kind, &points) {
+        let name = Path::new(&args[3]).file_stem().unwrap_or_default();
+        println!("{} : {}", name.to_string_lossy(), optimum);
+    }
     Ok(())
 }
@@ -0,0 +1,168 @@
+use crate::diversity::Diversity;
+use crate::schedule::Phase;
+use crate::termination::{self, StopReason};
+
+/// Something that happened during a run.
+#[derive(Clone, Copy, Debug)]
+pub enum Event<'a> {
+    /// The Held-Karp lower bound was computed before the run started.
+    Bound { value: f64 },
+    /// A tour shorter than any found before, or the initial tour.
+    NewBest { evals: usize, tour: &'a [usize], length: f64 },
+    /// The best length so far, reported every 10 evaluations.
+    Progress { evals: usize, best: f64 },
+    /// Statistics reported every `diversity_interval` evaluations.
+    Statistics { evals: usize, statistics: Statistics<'a> },
+    /// The search changed course.
+    PhaseChange { evals: usize, change: PhaseChange<'a> },
+    /// The final uses, mean credit and selection probability of an
+    /// adaptively selected operator.
+    Operator { name: &'static str, uses: usize, credit: f64, probability: f64 },
+    /// The run's state was saved to the checkpoint file, or could not be.
+    Checkpoint { evals: usize, error: Option<&'static str> },
+    /// The run stopped. This is the last event of a run.
+    Stop { evals: usize, reason: StopReason, best: f64, lower_bound: Option<f64> },
+}
+
+/// The periodic statistics of each algorithm.
+#[derive(Clone, Copy, Debug)]
+pub enum Statistics<'a> {
+    /// The diversity of the GA's population or the ant colony.
+    Population(&'a Diversity),
+    Annealing { temperature: f64, current: f64 },
+    Tabu { iteration: usize, current: f64 },
+    IteratedLocalSearch { current: f64 },
+}
+
+/// The ways a search can change course.
+#[derive(Clone, Copy, Debug)]
+pub enum PhaseChange<'a> {
+    /// The operator schedule moved on to the phase with the given index.
+    Schedule { index: usize, phase: &'a Phase },
+    /// The population stagnated, and the response was applied to it.
+    Stagnation { trigger: &'static str, response: &'static str },
+    /// Simulated annealing raised the temperature.
+    Reheat { temperature: f64 },
+    /// Iterated local search started again from a new tour.
+    Restart,
+}
+
+/// Whether the run should carry on after an event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+    Continue,
+    /// Stops the run at the next check of its stop conditions, with the
+    /// reason `cancelled`.
+    Stop,
+}
+
+/// Receives the events of a run as they happen.
+pub trait Observer {
+    fn notify(&mut self, event: &Event) -> Signal;
+}
+
+/// Any closure taking an event can observe a run.
+impl<F: FnMut(&Event) -> Signal> Observer for F {
+    fn notify(&mut self, event: &Event) -> Signal {
+        self(event)
+    }
+}
+
+/// Ignores every event.
+pub struct Silent;
+
+impl Observer for Silent {
+    fn notify(&mut self, _event: &Event) -> Signal {
+        Signal::Continue
+    }
+}
+
+/// Prints one line per event to standard output, as the command line
+/// program does. New best tours are not printed.
+pub struct Printer;
+
+impl Observer for Printer {
+    fn notify(&mut self, event: &Event) -> Signal {
+        match *event {
+            Event::Bound { value } => println!("bound {}", value),
+            Event::NewBest { .. } => (),
+            Event::Progress { evals, best } => println!("{} {}", evals, best),
+            Event::Statistics { evals, statistics } => match statistics {
+                Statistics::Population(diversity) => println!(
+                    "diversity {} bond {:.2} entropy {:.4} unique {}",
+                    evals, diversity.mean_bond_distance, diversity.edge_entropy,
+                    diversity.unique_tours
+                ),
+                Statistics::Annealing { temperature, current } => println!(
+                    "annealing {} temperature {:.6} current {}",
+                    evals, temperature, current
+                ),
+                Statistics::Tabu { iteration, current } =>
+                    println!("tabu {} iteration {} current {}", evals, iteration, current),
+                Statistics::IteratedLocalSearch { current } =>
+                    println!("ils {} current {}", evals, current),
+            },
+            Event::PhaseChange { evals, change } => match change {
+                PhaseChange::Schedule { index, .. } => println!("phase {} {}", evals, index),
+                PhaseChange::Stagnation { trigger, response } =>
+                    println!("stagnation {} {} {}", evals, trigger, response),
+                PhaseChange::Reheat { temperature } =>
+                    println!("reheat {} {}", evals, temperature),
+                PhaseChange::Restart => println!("restart {}", evals),
+            },
+            Event::Operator { name, uses, credit, probability } => println!(
+                "operator {} uses {} credit {:.6} probability {:.3}",
+                name, uses, credit, probability
+            ),
+            Event::Checkpoint { evals, error } => match error {
+                None => println!("checkpoint {}", evals),
+                Some(error) => println!("checkpoint {} failed: {}", evals, error),
+            },
+            Event::Stop { evals, reason, best, lower_bound } => {
+                println!("stop {} {}", evals, reason.name());
+                if let Some(bound) = lower_bound {
+                    println!("gap {:.4}", termination::gap(best, bound));
+                }
+            }
+        }
+        Signal::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Algorithm, Config};
+    use crate::solver::{Budget, Solver};
+    use crate::weighted_graph::{self, WeightedGraph};
+
+    #[test]
+    fn observers_can_stop_every_algorithm() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(20, 1));
+
+        for algorithm in Algorithm::ALL {
+            let args = [
+                "tsp", "unused", "10", "1000000", "--seed", "1",
+                "--algorithm", algorithm.name(), "--operator_selection", "bandit",
+            ];
+            let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            let budget = Budget::from_config(&config);
+            let mut new_bests = 0;
+            let mut stopped = false;
+            let result = config.solve(&graph, &budget, &mut |event: &Event| {
+                stopped = matches!(event, Event::Stop { .. });
+                match *event {
+                    Event::NewBest { .. } => new_bests += 1,
+                    Event::Progress { evals, .. } if evals >= 1000 => return Signal::Stop,
+                    _ => (),
+                }
+                Signal::Continue
+            });
+
+            assert_eq!(StopReason::Cancelled, result.stop_reason, "{}", algorithm.name());
+            assert!(result.evaluations < 1000000, "{}", algorithm.name());
+            assert!(new_bests > 0, "{}", algorithm.name());
+            assert!(stopped, "{}", algorithm.name());
+        }
+    }
+}
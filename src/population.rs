@@ -0,0 +1,409 @@
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand::distributions::uniform::SampleUniform;
+use rand_chacha::ChaCha8Rng;
+use std::thread;
+
+use crate::diversity::EdgeFrequencies;
+use crate::genotype::Genotype;
+use crate::local_search::LocalSearch;
+use crate::schedule::{Crossover, Mutation, Survivor};
+use crate::weighted_graph::WeightedGraph;
+
+/// A newly bred and evaluated child, with the indices of its parents in the
+/// population it was bred from.
+pub struct Child {
+    pub genotype: Genotype,
+    pub fitness: f64,
+    pub parents: (usize, usize),
+}
+
+/// The parents and operators chosen for a child before it is bred.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plan {
+    pub parents: (usize, usize),
+    pub crossover: Crossover,
+    pub mutation: Mutation,
+}
+
+/// Breeds and evaluates the planned children of the population, improving
+/// each by the local search, if any, with the given candidate lists. The
+/// plans are split into one run of consecutive children per random number
+/// generator, each bred on its own thread with its own generator, so the
+/// children depend only on the plans and the generators' states.
+pub fn breed(plans: &[Plan], pop_and_fitness: &[(Genotype, f64)], graph: &WeightedGraph,
+             local_search: Option<(LocalSearch, &[Vec<usize>])>,
+             rngs: &mut [ChaCha8Rng]) -> Vec<Child> {
+    let breed_one = |plan: &Plan, rng: &mut ChaCha8Rng| {
+        let (parent1, parent2) = plan.parents;
+        let child = plan.crossover.apply(
+            &pop_and_fitness[parent1].0, &pop_and_fitness[parent2].0, rng
+        );
+        let mut child = plan.mutation.apply(child, rng);
+        if let Some((search, candidates)) = local_search {
+            let mut tour = child.data().to_vec();
+            search.apply(&mut tour, graph, candidates);
+            child = Genotype::from_data(tour);
+        }
+        let fitness = crate::fitness(graph, child.data());
+        Child { genotype: child, fitness, parents: plan.parents }
+    };
+
+    if rngs.len() == 1 || plans.len() <= 1 {
+        return plans.iter().map(|plan| breed_one(plan, &mut rngs[0])).collect();
+    }
+    let breed_one = &breed_one;
+    let chunk_size = plans.len().div_ceil(rngs.len());
+    thread::scope(|scope| {
+        let handles = plans.chunks(chunk_size)
+            .zip(rngs.iter_mut())
+            .map(|(chunk, rng)| scope.spawn(move || {
+                chunk.iter().map(|plan| breed_one(plan, rng)).collect::<Vec<Child>>()
+            }))
+            .collect::<Vec<_>>();
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// How each generation's offspring are merged into the population of size μ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PopulationModel {
+    /// One child per generation, inserted by the schedule's survivor operator.
+    SteadyState,
+    /// μ minus `elites` children replace all but the `elites` best parents.
+    Generational { elites: usize },
+    /// (μ+λ): the best μ of parents and λ children survive.
+    PlusLambda { lambda: usize },
+    /// (μ,λ): the best μ of λ >= μ children survive; all parents are dropped.
+    CommaLambda { lambda: usize },
+}
+
+impl PopulationModel {
+    /// Parses a model of the form `steady`, `generational:<elites>`,
+    /// `plus:<lambda>` or `comma:<lambda>`.
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let (name, param) = match spec.trim().split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (spec.trim(), None),
+        };
+        let param = match param {
+            None => None,
+            Some(param) => Some(param.parse::<usize>()
+                .map_err(|_| "could not parse population model parameter")?),
+        };
+
+        match (name, param) {
+            ("steady", None) => Ok(Self::SteadyState),
+            ("generational", elites) =>
+                Ok(Self::Generational { elites: elites.unwrap_or(1) }),
+            ("plus", Some(lambda)) if lambda > 0 =>
+                Ok(Self::PlusLambda { lambda }),
+            ("comma", Some(lambda)) if lambda > 0 =>
+                Ok(Self::CommaLambda { lambda }),
+            _ => Err("unknown population model"),
+        }
+    }
+
+    /// Checks that the model can maintain a population of size pop_size.
+    pub fn validate(self, pop_size: usize) -> Result<(), &'static str> {
+        match self {
+            Self::Generational { elites } if elites >= pop_size =>
+                Err("number of elites must be less than the pop size"),
+            Self::CommaLambda { lambda } if lambda < pop_size =>
+                Err("comma selection requires lambda of at least the pop size"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the number of children bred each generation.
+    pub fn num_offspring(self, pop_size: usize) -> usize {
+        match self {
+            Self::SteadyState => 1,
+            Self::Generational { elites } => pop_size - elites,
+            Self::PlusLambda { lambda } | Self::CommaLambda { lambda } => lambda,
+        }
+    }
+
+    /// Merges offspring into the population, which must be sorted by fitness
+    /// and remains so afterwards. The survivor operator and distribution are
+    /// only used by the steady-state model. If reject_duplicates is set,
+    /// children whose tour is already in the population are discarded, unless
+    /// the population cannot otherwise be filled. A generation with fewer
+    /// children than the model breeds, as the last of a run may be, keeps
+    /// enough of the best parents to stay at the same size.
+    pub fn advance<X>(self, pop_and_fitness: &mut Vec<(Genotype, f64)>,
+                      offspring: Vec<Child>,
+                      survivor: Survivor,
+                      survivor_dist: &WeightedIndex<X>,
+                      reject_duplicates: bool,
+                      rng: &mut ChaCha8Rng)
+    where X: SampleUniform + PartialOrd
+    {
+        let pop_size = pop_and_fitness.len();
+        if self == Self::SteadyState {
+            for child in offspring {
+                if reject_duplicates
+                    && contains_tour(pop_and_fitness, &child.genotype, child.fitness) {
+                    continue;
+                }
+                let parents = child.parents;
+                let child = (child.genotype, child.fitness);
+                match survivor {
+                    Survivor::Worst => replace_worst(pop_and_fitness, child),
+                    Survivor::Rank => replace_with_probability(
+                        pop_and_fitness, child, survivor_dist, rng
+                    ),
+                    Survivor::Crowding => deterministic_crowding(
+                        pop_and_fitness, child, parents
+                    ),
+                    Survivor::Entropy => replace_for_entropy(
+                        pop_and_fitness, child
+                    ),
+                }
+            }
+            return;
+        }
+
+        let mut offspring = offspring
+            .into_iter()
+            .map(|child| (child.genotype, child.fitness))
+            .collect::<Vec<(Genotype, f64)>>();
+        match self {
+            Self::Generational { elites } => {
+                pop_and_fitness.truncate(elites.max(pop_size.saturating_sub(offspring.len())));
+                pop_and_fitness.append(&mut offspring);
+            }
+            Self::CommaLambda { .. } => {
+                pop_and_fitness.truncate(pop_size.saturating_sub(offspring.len()));
+                pop_and_fitness.append(&mut offspring);
+            }
+            _ => {
+                pop_and_fitness.append(&mut offspring);
+            }
+        }
+
+        pop_and_fitness.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+        if reject_duplicates {
+            truncate_unique(pop_and_fitness, pop_size);
+        } else {
+            pop_and_fitness.truncate(pop_size);
+        }
+    }
+}
+
+/// Returns true if the tour is already in the population. Only individuals
+/// with (nearly) the same fitness are compared tour by tour.
+pub fn contains_tour(pop_and_fitness: &[(Genotype, f64)], genotype: &Genotype,
+                     fitness: f64) -> bool {
+    pop_and_fitness
+        .iter()
+        .filter(|x| (x.1 - fitness).abs() <= 1e-9 * fitness.abs())
+        .any(|x| x.0.same_tour(genotype))
+}
+
+/// Keeps the best pop_size distinct tours of a sorted population, padding
+/// with the best duplicates if there are not enough distinct ones.
+fn truncate_unique(pop_and_fitness: &mut Vec<(Genotype, f64)>, pop_size: usize) {
+    let mut unique: Vec<(Genotype, f64)> = Vec::with_capacity(pop_size);
+    let mut duplicates = Vec::new();
+    for x in pop_and_fitness.drain(..) {
+        if unique.len() == pop_size {
+            break;
+        }
+        if contains_tour(&unique, &x.0, x.1) {
+            duplicates.push(x);
+        } else {
+            unique.push(x);
+        }
+    }
+
+    let missing = pop_size - unique.len();
+    unique.extend(duplicates.into_iter().take(missing));
+    unique.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+    *pop_and_fitness = unique;
+}
+
+/// Removes the individual at idx and inserts the child in sorted position.
+fn replace_at(pop_and_fitness: &mut Vec<(Genotype, f64)>, idx: usize,
+              child: (Genotype, f64)) {
+    pop_and_fitness.remove(idx);
+    let mut idx = 0;
+    for x in pop_and_fitness.iter() {
+        if child.1 > x.1 {
+            idx += 1;
+        }
+    }
+    pop_and_fitness.insert(idx, child);
+}
+
+/// The child competes only with whichever of its parents is closer to it in
+/// bond distance, replacing that parent if it is at least as fit.
+pub fn deterministic_crowding(pop_and_fitness: &mut Vec<(Genotype, f64)>,
+                              child: (Genotype, f64),
+                              parents: (usize, usize)) {
+    let (parent1, parent2) = parents;
+    let distance1 = child.0.bond_distance(&pop_and_fitness[parent1].0);
+    let distance2 = child.0.bond_distance(&pop_and_fitness[parent2].0);
+    let rival = if distance1 <= distance2 { parent1 } else { parent2 };
+
+    if child.1 <= pop_and_fitness[rival].1 {
+        replace_at(pop_and_fitness, rival, child);
+    }
+}
+
+/// Among the individuals less fit than the child, replaces the one whose
+/// removal leaves the population-wide edge distribution with the highest
+/// entropy. The child is discarded if it is no better than everyone.
+pub fn replace_for_entropy(pop_and_fitness: &mut Vec<(Genotype, f64)>,
+                           child: (Genotype, f64)) {
+    let frequencies = EdgeFrequencies::from_population(pop_and_fitness);
+
+    let mut best: Option<(usize, f64)> = None;
+    for (idx, x) in pop_and_fitness.iter().enumerate() {
+        if x.1 <= child.1 {
+            continue;
+        }
+        let entropy = frequencies.entropy_after_replacing(&x.0, &child.0);
+        if best.is_none_or(|(_, best_entropy)| entropy > best_entropy) {
+            best = Some((idx, entropy));
+        }
+    }
+
+    if let Some((idx, _)) = best {
+        replace_at(pop_and_fitness, idx, child);
+    }
+}
+
+pub fn replace_with_probability<X>(pop_and_fitness: &mut Vec<(Genotype, f64)>,
+                                   child: (Genotype, f64),
+                                   selection_dist: &WeightedIndex<X>,
+                                   rng: &mut ChaCha8Rng,
+                                   )
+where X: SampleUniform + PartialOrd
+{
+    let idx = selection_dist.sample(rng);
+    replace_at(pop_and_fitness, idx, child);
+}
+
+pub fn replace_worst(pop_and_fitness: &mut Vec<(Genotype, f64)>,
+                     child: (Genotype, f64)) {
+    let mut idx = 0;
+    for x in pop_and_fitness.iter() {
+        if child.1 > x.1 {
+            idx += 1;
+        }
+    }
+    pop_and_fitness.insert(idx, child);
+    pop_and_fitness.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn individuals(fitnesses: &[f64]) -> Vec<(Genotype, f64)> {
+        let mut rng = ChaCha8Rng::from_entropy();
+        fitnesses
+            .iter()
+            .map(|&fitness| (Genotype::random(5, &mut rng), fitness))
+            .collect()
+    }
+
+    fn children(fitnesses: &[f64]) -> Vec<Child> {
+        individuals(fitnesses)
+            .into_iter()
+            .map(|(genotype, fitness)| Child { genotype, fitness, parents: (0, 0) })
+            .collect()
+    }
+
+    fn fitnesses(pop_and_fitness: &[(Genotype, f64)]) -> Vec<f64> {
+        pop_and_fitness.iter().map(|x| x.1).collect()
+    }
+
+    #[test]
+    fn models_keep_the_right_survivors() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let dist = WeightedIndex::new([0.0, 1.0, 1.0]).unwrap();
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::Generational { elites: 1 }.advance(
+            &mut pop, children(&[5.0, 4.0]), Survivor::Worst, &dist, false, &mut rng
+        );
+        assert_eq!(vec![1.0, 4.0, 5.0], fitnesses(&pop));
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::PlusLambda { lambda: 2 }.advance(
+            &mut pop, children(&[0.5, 4.0]), Survivor::Worst, &dist, false, &mut rng
+        );
+        assert_eq!(vec![0.5, 1.0, 2.0], fitnesses(&pop));
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::CommaLambda { lambda: 4 }.advance(
+            &mut pop, children(&[9.0, 6.0, 8.0, 7.0]), Survivor::Worst,
+            &dist, false, &mut rng
+        );
+        assert_eq!(vec![6.0, 7.0, 8.0], fitnesses(&pop));
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::Generational { elites: 0 }.advance(
+            &mut pop, children(&[5.0]), Survivor::Worst, &dist, false, &mut rng
+        );
+        assert_eq!(vec![1.0, 2.0, 5.0], fitnesses(&pop));
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::CommaLambda { lambda: 4 }.advance(
+            &mut pop, children(&[9.0, 6.0]), Survivor::Worst, &dist, false, &mut rng
+        );
+        assert_eq!(vec![1.0, 6.0, 9.0], fitnesses(&pop));
+
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        PopulationModel::SteadyState.advance(
+            &mut pop, children(&[1.5]), Survivor::Worst, &dist, false, &mut rng
+        );
+        assert_eq!(vec![1.0, 1.5, 2.0], fitnesses(&pop));
+    }
+
+    #[test]
+    fn duplicates_are_rejected() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let dist = WeightedIndex::new([0.0, 1.0, 1.0]).unwrap();
+        let mut pop = individuals(&[1.0, 2.0, 3.0]);
+        let reflected = pop[0].0.data().iter().rev().copied().collect::<Vec<usize>>();
+        let clone = Child {
+            genotype: Genotype::from_data(reflected),
+            fitness: 1.0,
+            parents: (0, 0),
+        };
+
+        PopulationModel::SteadyState.advance(
+            &mut pop, vec![clone], Survivor::Worst, &dist, true, &mut rng
+        );
+        assert_eq!(vec![1.0, 2.0, 3.0], fitnesses(&pop));
+    }
+
+    #[test]
+    fn crowding_replaces_the_closer_parent() {
+        let mut pop = vec![
+            (Genotype::from_data(vec![0, 1, 2, 3, 4]), 1.0),
+            (Genotype::from_data(vec![0, 2, 4, 1, 3]), 2.0),
+        ];
+        let child = (Genotype::from_data(vec![0, 2, 4, 3, 1]), 1.5);
+
+        deterministic_crowding(&mut pop, child, (0, 1));
+        assert_eq!(vec![1.0, 1.5], fitnesses(&pop));
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        assert!(PopulationModel::parse("plus").is_err());
+        assert!(PopulationModel::parse("comma:0").is_err());
+        assert!(PopulationModel::parse("comma:10").unwrap().validate(20).is_err());
+        assert!(PopulationModel::parse("generational:20").unwrap().validate(20).is_err());
+        assert_eq!(
+            PopulationModel::Generational { elites: 1 },
+            PopulationModel::parse("generational").unwrap()
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use rand_chacha::ChaCha8Rng;
+
+use crate::diversity::Diversity;
+use crate::genotype::Genotype;
+use crate::weighted_graph::WeightedGraph;
+
+/// What to do to the population once it is found to have stagnated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Response {
+    /// Replaces all but the `elites` best individuals with random tours.
+    Reseed { elites: usize },
+    /// Applies `strength` inversion mutations to every individual except the
+    /// best.
+    Hypermutation { strength: usize },
+    /// Replaces the whole population with random tours, except the best.
+    Restart,
+}
+
+impl Response {
+    /// Parses a response of the form `reseed:<elites>`,
+    /// `hypermutation:<strength>` or `restart`.
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let (name, param) = match spec.trim().split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (spec.trim(), None),
+        };
+        let param = match param {
+            None => None,
+            Some(param) => Some(param.parse::<usize>()
+                .map_err(|_| "could not parse stagnation response parameter")?),
+        };
+
+        match (name, param) {
+            ("reseed", Some(elites)) if elites > 0 =>
+                Ok(Self::Reseed { elites }),
+            ("hypermutation", Some(strength)) if strength > 0 =>
+                Ok(Self::Hypermutation { strength }),
+            ("restart", None) => Ok(Self::Restart),
+            _ => Err("unknown stagnation response"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Reseed { .. } => "reseed",
+            Self::Hypermutation { .. } => "hypermutation",
+            Self::Restart => "restart",
+        }
+    }
+
+    /// Applies the response to a population sorted by fitness, leaving it
+    /// sorted. At most `max_evals` individuals, the worst, are changed, so
+    /// that the response stays within the run's remaining budget.
+    /// Returns the number of fitness evaluations used.
+    pub fn apply(self, pop_and_fitness: &mut [(Genotype, f64)], graph: &WeightedGraph,
+                 max_evals: usize, rng: &mut ChaCha8Rng) -> usize {
+        let pop_size = pop_and_fitness.len();
+        let num_alleles = graph.num_vertices();
+        let keep = match self {
+            Self::Reseed { elites } => elites.min(pop_size),
+            Self::Hypermutation { .. } | Self::Restart => 1,
+        };
+        let keep = keep.max(pop_size.saturating_sub(max_evals));
+
+        for individual in pop_and_fitness[keep..].iter_mut() {
+            let genotype = match self {
+                Self::Hypermutation { strength } => {
+                    let mut genotype = individual.0.clone();
+                    for _ in 0..strength {
+                        genotype = genotype.inversion_mutation(rng);
+                    }
+                    genotype
+                }
+                Self::Reseed { .. } | Self::Restart =>
+                    Genotype::random(num_alleles, rng),
+            };
+            let fitness = crate::fitness(graph, genotype.data());
+            *individual = (genotype, fitness);
+        }
+        pop_and_fitness.sort_by(|x, y| x.1.partial_cmp(&y.1).unwrap());
+
+        pop_size - keep
+    }
+}
+
+/// Why a population was judged to have stagnated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trigger {
+    NoImprovement,
+    LowDiversity,
+}
+
+impl Trigger {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NoImprovement => "no-improvement",
+            Self::LowDiversity => "low-diversity",
+        }
+    }
+}
+
+/// Detects stagnation, either because the best fitness has not improved for
+/// `window` evaluations or because the mean bond distance of the population
+/// has fallen below `min_bond_distance`. Either check is disabled by 0.
+pub struct StagnationDetector {
+    window: usize,
+    min_bond_distance: f64,
+    best: f64,
+    last_improvement: usize,
+    last_diversity_check: usize,
+}
+
+impl StagnationDetector {
+    pub fn new(window: usize, min_bond_distance: f64, initial_best: f64) -> Self {
+        Self {
+            window,
+            min_bond_distance,
+            best: initial_best,
+            last_improvement: 0,
+            last_diversity_check: 0,
+        }
+    }
+
+    /// Records the state of the population after `evals` evaluations and
+    /// returns the trigger if it has stagnated. Diversity is only measured
+    /// once every pop size evaluations, as it is costly to compute.
+    pub fn check(&mut self, evals: usize, pop_and_fitness: &[(Genotype, f64)])
+    -> Option<Trigger> {
+        let best = pop_and_fitness[0].1;
+        if best < self.best {
+            self.best = best;
+            self.last_improvement = evals;
+        }
+
+        if self.window > 0 && evals - self.last_improvement >= self.window {
+            return Some(Trigger::NoImprovement);
+        }
+
+        if self.min_bond_distance > 0.0
+            && evals - self.last_diversity_check >= pop_and_fitness.len() {
+            self.last_diversity_check = evals;
+            let diversity = Diversity::measure(pop_and_fitness);
+            if diversity.mean_bond_distance < self.min_bond_distance {
+                return Some(Trigger::LowDiversity);
+            }
+        }
+        None
+    }
+
+    /// Returns the best fitness, the evaluation at which it last improved and
+    /// that of the last diversity check, as saved in a checkpoint.
+    pub fn state(&self) -> (f64, usize, usize) {
+        (self.best, self.last_improvement, self.last_diversity_check)
+    }
+
+    /// Restores the state saved by `state`.
+    pub fn restore(&mut self, state: (f64, usize, usize)) {
+        (self.best, self.last_improvement, self.last_diversity_check) = state;
+    }
+
+    /// Starts the stagnation checks afresh, as after a response.
+    pub fn reset(&mut self, evals: usize, best: f64) {
+        self.best = best;
+        self.last_improvement = evals;
+        self.last_diversity_check = evals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn responses_keep_the_best_tour() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let points = (0..8)
+            .map(|i| (i as f64, (i * i) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+
+        for (response, expected_evals) in [
+            (Response::Reseed { elites: 2 }, 8),
+            (Response::Hypermutation { strength: 3 }, 9),
+            (Response::Restart, 9),
+        ] {
+            let mut pop = crate::initial_population(10, 8, &[], &graph, &mut rng);
+            let best = pop[0].clone();
+
+            let evals = response.apply(&mut pop, &graph, usize::MAX, &mut rng);
+            assert_eq!(10, pop.len());
+            assert!(pop.contains(&best));
+            assert!(pop.windows(2).all(|x| x[0].1 <= x[1].1));
+            assert_eq!(expected_evals, evals);
+
+            let evals = response.apply(&mut pop, &graph, 3, &mut rng);
+            assert_eq!(3, evals);
+        }
+    }
+
+    #[test]
+    fn stagnation_is_detected_after_the_window() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let pop = vec![(Genotype::random(5, &mut rng), 10.0)];
+        let mut detector = StagnationDetector::new(100, 0.0, 20.0);
+
+        assert_eq!(None, detector.check(50, &pop));
+        assert_eq!(None, detector.check(149, &pop));
+        assert_eq!(Some(Trigger::NoImprovement), detector.check(150, &pop));
+
+        detector.reset(150, 10.0);
+        assert_eq!(None, detector.check(200, &pop));
+    }
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use crate::diversity::Diversity;
+use crate::termination::{self, StopReason};
+
+/// The outcome of a run of the algorithm.
+#[derive(Clone, Debug)]
+pub struct RunResult {
+    /// The best tour found, as a sequence of city indices.
+    pub best_tour: Vec<usize>,
+    pub best_length: f64,
+    pub evaluations: usize,
+    pub elapsed: Duration,
+    pub stop_reason: StopReason,
+    /// Diversity of the final population.
+    pub diversity: Diversity,
+    /// A lower bound on the optimal tour length, if one was given or computed.
+    pub lower_bound: Option<f64>,
+}
+
+impl RunResult {
+    /// Returns the percentage by which the best tour exceeds the lower bound.
+    pub fn gap(&self) -> Option<f64> {
+        self.lower_bound.map(|bound| termination::gap(self.best_length, bound))
+    }
+}
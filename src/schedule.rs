@@ -0,0 +1,324 @@
+use rand_chacha::ChaCha8Rng;
+
+use crate::genotype::{Genotype, Move};
+
+/// The mutation operators a phase may apply to each child.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mutation {
+    Swap,
+    Inversion,
+    Insert,
+}
+
+impl Mutation {
+    pub const ALL: [Self; 3] = [Self::Swap, Self::Inversion, Self::Insert];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.name() == name.trim())
+            .ok_or("unknown mutation operator")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Swap => "swap",
+            Self::Inversion => "inversion",
+            Self::Insert => "insert",
+        }
+    }
+
+    pub fn apply(self, genotype: Genotype, rng: &mut ChaCha8Rng) -> Genotype {
+        match self {
+            Self::Swap => genotype.swap_mutation(rng),
+            Self::Inversion => genotype.inversion_mutation(rng),
+            Self::Insert => genotype.insert_mutation(rng),
+        }
+    }
+
+    /// Chooses the move the operator would make to a tour of the given size,
+    /// without making it.
+    pub fn random_move(self, num_alleles: usize, rng: &mut ChaCha8Rng) -> Move {
+        match self {
+            Self::Swap => Move::random_swap(num_alleles, rng),
+            Self::Inversion => Move::random_inversion(num_alleles, rng),
+            Self::Insert => Move::random_insert(num_alleles, rng),
+        }
+    }
+}
+
+/// The recombination operators a phase may use to produce a child.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crossover {
+    Edge,
+    Order,
+}
+
+impl Crossover {
+    pub const ALL: [Self; 2] = [Self::Edge, Self::Order];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.name() == name.trim())
+            .ok_or("unknown crossover operator")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Edge => "edge",
+            Self::Order => "order",
+        }
+    }
+
+    pub fn apply(self, parent1: &Genotype, parent2: &Genotype,
+                 rng: &mut ChaCha8Rng) -> Genotype {
+        match self {
+            Self::Edge => Genotype::edge_crossover(parent1, parent2, rng),
+            Self::Order => Genotype::order_crossover(parent1, parent2, rng),
+        }
+    }
+}
+
+/// The survivor selection operators a phase may use to insert a child.
+/// `Rank` replaces an individual chosen with reversed rank-based probability,
+/// `Worst` always replaces the least fit individual, `Crowding` lets the child
+/// replace the closer of its parents if fitter, and `Entropy` replaces the
+/// less fit individual whose removal best preserves edge diversity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Survivor {
+    Rank,
+    Worst,
+    Crowding,
+    Entropy,
+}
+
+impl Survivor {
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        match name.trim() {
+            "rank" => Ok(Self::Rank),
+            "worst" => Ok(Self::Worst),
+            "crowding" => Ok(Self::Crowding),
+            "entropy" => Ok(Self::Entropy),
+            _ => Err("unknown survivor operator"),
+        }
+    }
+}
+
+/// The condition under which a phase hands over to the next one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PhaseEnd {
+    /// Ends once the total number of evaluations reaches the given count.
+    Evals(usize),
+    /// Ends once the given fraction (0.0 to 1.0) of the run's budget is used.
+    Fraction(f64),
+    /// Ends after the given number of evaluations without a new best.
+    Stagnation(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Phase {
+    pub mutation: Mutation,
+    pub crossover: Crossover,
+    pub survivor: Survivor,
+    /// None means the phase lasts until the end of the run.
+    pub end: Option<PhaseEnd>,
+}
+
+impl Phase {
+    /// Parses a phase of the form `mutation,crossover,survivor[@end]`, where
+    /// end is an evaluation count (`1000`), a percentage of the budget
+    /// (`25%`) or a stagnation window (`stall:500`).
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let (operators, end) = match spec.split_once('@') {
+            Some((operators, end)) => (operators, Some(end.trim())),
+            None => (spec, None),
+        };
+
+        let operators = operators.split(',').collect::<Vec<&str>>();
+        if operators.len() != 3 {
+            return Err("phase must name a mutation, crossover and survivor");
+        }
+
+        let end = match end {
+            None => None,
+            Some(end) => Some(Self::parse_end(end)?),
+        };
+
+        Ok(Self {
+            mutation: Mutation::parse(operators[0])?,
+            crossover: Crossover::parse(operators[1])?,
+            survivor: Survivor::parse(operators[2])?,
+            end,
+        })
+    }
+
+    fn parse_end(end: &str) -> Result<PhaseEnd, &'static str> {
+        if let Some(window) = end.strip_prefix("stall:") {
+            return match window.parse::<usize>() {
+                Err(_) => Err("could not parse stagnation window"),
+                Ok(window) => Ok(PhaseEnd::Stagnation(window)),
+            };
+        }
+        if let Some(percent) = end.strip_suffix('%') {
+            return match percent.parse::<f64>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) =>
+                    Ok(PhaseEnd::Fraction(percent / 100.0)),
+                _ => Err("could not parse budget percentage"),
+            };
+        }
+        match end.parse::<usize>() {
+            Err(_) => Err("could not parse phase end"),
+            Ok(evals) => Ok(PhaseEnd::Evals(evals)),
+        }
+    }
+}
+
+/// An ordered list of phases, each specifying which operators are in use
+/// until its end condition is met.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    phases: Vec<Phase>,
+}
+
+impl Schedule {
+    /// Parses a schedule of phases separated by `;`, for example
+    /// `swap,edge,rank@1000;inversion,edge,worst`.
+    pub fn parse(spec: &str) -> Result<Self, &'static str> {
+        let phases = spec
+            .split(';')
+            .filter(|phase| !phase.trim().is_empty())
+            .map(Phase::parse)
+            .collect::<Result<Vec<Phase>, &'static str>>()?;
+        if phases.is_empty() {
+            return Err("schedule must contain at least one phase");
+        }
+        Ok(Self { phases })
+    }
+
+    pub fn phases(&self) -> &[Phase] {
+        &self.phases
+    }
+}
+
+impl Default for Schedule {
+    /// Swap mutation with rank-based replacement for the first 1000
+    /// evaluations, then inversion mutation with replace worst.
+    fn default() -> Self {
+        Self::parse("swap,edge,rank@1000;inversion,edge,worst").unwrap()
+    }
+}
+
+/// Tracks which phase of a schedule is active as a run progresses.
+pub struct ScheduleState<'a> {
+    schedule: &'a Schedule,
+    phase_idx: usize,
+    best: f64,
+    last_improvement: usize,
+}
+
+impl<'a> ScheduleState<'a> {
+    pub fn new(schedule: &'a Schedule, initial_best: f64) -> Self {
+        Self {
+            schedule,
+            phase_idx: 0,
+            best: initial_best,
+            last_improvement: 0,
+        }
+    }
+
+    pub fn phase(&self) -> &Phase {
+        &self.schedule.phases[self.phase_idx]
+    }
+
+    pub fn phase_idx(&self) -> usize {
+        self.phase_idx
+    }
+
+    /// Returns the active phase's index, the best fitness and the evaluation
+    /// at which it last improved, as saved in a checkpoint.
+    pub fn state(&self) -> (usize, f64, usize) {
+        (self.phase_idx, self.best, self.last_improvement)
+    }
+
+    /// Restores the state saved by `state`, which must be for a schedule with
+    /// as many phases.
+    pub fn restore(&mut self, (phase_idx, best, last_improvement): (usize, f64, usize)) {
+        assert!(phase_idx < self.schedule.phases.len(), "schedule state of wrong length");
+        self.phase_idx = phase_idx;
+        self.best = best;
+        self.last_improvement = last_improvement;
+    }
+
+    /// Records the best fitness after `evals` evaluations, with `progress`
+    /// the fraction of the run's budget used so far, moving on to later
+    /// phases whose predecessors have ended.
+    /// Returns true if the active phase changed.
+    pub fn update(&mut self, evals: usize, progress: f64, best: f64)
+    -> bool {
+        if best < self.best {
+            self.best = best;
+            self.last_improvement = evals;
+        }
+
+        let start_idx = self.phase_idx;
+        while let Some(end) = self.phase().end {
+            let ended = match end {
+                PhaseEnd::Evals(count) => evals >= count,
+                PhaseEnd::Fraction(fraction) => progress >= fraction,
+                PhaseEnd::Stagnation(window) =>
+                    evals - self.last_improvement >= window,
+            };
+            if !ended || self.phase_idx + 1 == self.schedule.phases.len() {
+                break;
+            }
+            self.phase_idx += 1;
+            // A stagnation window in the new phase counts from its start.
+            self.last_improvement = evals;
+        }
+        self.phase_idx != start_idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_is_parsed_correctly() {
+        let schedule = Schedule::parse(
+            "swap,edge,rank@1000; inversion,edge,worst@25%;swap,edge,worst@stall:50"
+        ).unwrap();
+        let phases = schedule.phases();
+
+        assert_eq!(3, phases.len());
+        assert_eq!(Mutation::Swap, phases[0].mutation);
+        assert_eq!(Survivor::Rank, phases[0].survivor);
+        assert_eq!(Some(PhaseEnd::Evals(1000)), phases[0].end);
+        assert_eq!(Mutation::Inversion, phases[1].mutation);
+        assert_eq!(Some(PhaseEnd::Fraction(0.25)), phases[1].end);
+        assert_eq!(Some(PhaseEnd::Stagnation(50)), phases[2].end);
+
+        assert!(Schedule::parse("swap,edge").is_err());
+        assert!(Schedule::parse("swap,pmx,rank").is_err());
+    }
+
+    #[test]
+    fn phases_advance_on_their_end_conditions() {
+        let schedule = Schedule::parse(
+            "swap,edge,rank@10;inversion,edge,worst@stall:5;swap,edge,worst"
+        ).unwrap();
+        let mut state = ScheduleState::new(&schedule, 100.0);
+
+        assert!(!state.update(9, 0.09, 90.0));
+        assert_eq!(0, state.phase_idx());
+        assert!(state.update(10, 0.10, 90.0));
+        assert_eq!(1, state.phase_idx());
+
+        // Improvement resets the stagnation window.
+        assert!(!state.update(14, 0.14, 80.0));
+        assert!(!state.update(18, 0.18, 80.0));
+        assert!(state.update(19, 0.19, 80.0));
+        assert_eq!(2, state.phase_idx());
+    }
+}
@@ -0,0 +1,253 @@
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand_chacha::ChaCha8Rng;
+
+use crate::genotype::Genotype;
+
+/// Chooses parents from a population sorted by fitness, best first.
+pub trait ParentSelection: Send + Sync {
+    /// Returns the indices of `count` parents, possibly with repeats.
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize>;
+
+    /// Returns the selection weights of a population of the given size, best
+    /// first, if they depend only on rank.
+    fn rank_weights(&self, _pop_size: usize) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+/// A parent selection scheme prepared for populations of one size, so that
+/// rank-based schemes build their distribution once rather than per call.
+pub struct Prepared<'a> {
+    scheme: &'a dyn ParentSelection,
+    by_rank: Option<WeightedIndex<f64>>,
+}
+
+impl<'a> Prepared<'a> {
+    pub fn new(scheme: &'a dyn ParentSelection, pop_size: usize) -> Self {
+        let by_rank = scheme.rank_weights(pop_size)
+            .map(|weights| WeightedIndex::new(weights).unwrap());
+        Self { scheme, by_rank }
+    }
+
+    /// Returns the indices of `count` parents, possibly with repeats.
+    pub fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+                  rng: &mut ChaCha8Rng) -> Vec<usize> {
+        match &self.by_rank {
+            Some(dist) => (0..count).map(|_| dist.sample(rng)).collect(),
+            None => self.scheme.select(pop_and_fitness, count, rng),
+        }
+    }
+}
+
+/// Parses a parent selection scheme: `rank:<s>` for linear ranking with
+/// selection pressure s in (1, 2], `tournament:<k>`, `exponential:<c>` with
+/// base c in (0, 1), `proportional` or `sus`.
+pub fn parse(spec: &str) -> Result<Box<dyn ParentSelection>, &'static str> {
+    let (name, param) = match spec.trim().split_once(':') {
+        Some((name, param)) => (name, Some(param)),
+        None => (spec.trim(), None),
+    };
+
+    match (name, param) {
+        ("rank", param) => {
+            let s = match param {
+                None => 2.0,
+                Some(s) => s.parse::<f64>()
+                    .map_err(|_| "could not parse selection pressure")?,
+            };
+            if s <= 1.0 || s > 2.0 {
+                return Err("linear rank selection pressure must be in (1, 2]");
+            }
+            Ok(Box::new(LinearRank { s }))
+        }
+        ("tournament", Some(k)) => {
+            let k = k.parse::<usize>()
+                .map_err(|_| "could not parse tournament size")?;
+            if k == 0 {
+                return Err("tournament size must be positive");
+            }
+            Ok(Box::new(Tournament { k }))
+        }
+        ("exponential", Some(c)) => {
+            let c = c.parse::<f64>()
+                .map_err(|_| "could not parse exponential base")?;
+            if c <= 0.0 || c >= 1.0 {
+                return Err("exponential rank base must be in (0, 1)");
+            }
+            Ok(Box::new(ExponentialRank { c }))
+        }
+        ("proportional", None) => Ok(Box::new(FitnessProportional)),
+        ("sus", None) => Ok(Box::new(StochasticUniversal)),
+        _ => Err("unknown parent selection scheme"),
+    }
+}
+
+/// Linear ranking as described by Eiben & Smith, where the best individual
+/// is expected to be selected s times across N selections.
+pub struct LinearRank {
+    pub s: f64,
+}
+
+impl ParentSelection for LinearRank {
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+        sample_weighted(self.rank_weights(pop_and_fitness.len()).unwrap(), count, rng)
+    }
+
+    fn rank_weights(&self, pop_size: usize) -> Option<Vec<f64>> {
+        Some((0..pop_size)
+            .rev()
+            .map(|rank| probability_by_rank(self.s, rank, pop_size))
+            .collect())
+    }
+}
+
+/// Picks the best of k individuals drawn uniformly with replacement.
+pub struct Tournament {
+    pub k: usize,
+}
+
+impl ParentSelection for Tournament {
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+        let pop_size = pop_and_fitness.len();
+        // The population is sorted, so the smallest index is the fittest.
+        (0..count)
+            .map(|_| {
+                (0..self.k)
+                    .map(|_| rng.gen_range(0..pop_size))
+                    .min()
+                    .unwrap()
+            })
+            .collect()
+    }
+}
+
+/// Exponential ranking, where the individual of rank i (0 being the best)
+/// is selected with probability proportional to c^i.
+pub struct ExponentialRank {
+    pub c: f64,
+}
+
+impl ParentSelection for ExponentialRank {
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+        sample_weighted(self.rank_weights(pop_and_fitness.len()).unwrap(), count, rng)
+    }
+
+    fn rank_weights(&self, pop_size: usize) -> Option<Vec<f64>> {
+        Some((0..pop_size).map(|rank| self.c.powi(rank as i32)).collect())
+    }
+}
+
+/// Roulette wheel selection with probability proportional to the inverse of
+/// each individual's tour length.
+pub struct FitnessProportional;
+
+impl ParentSelection for FitnessProportional {
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+        sample_weighted(inverse_lengths(pop_and_fitness).collect(), count, rng)
+    }
+}
+
+/// Stochastic universal sampling over the same probabilities as fitness
+/// proportional selection: all parents are chosen with a single spin of a
+/// wheel with `count` evenly spaced pointers, minimising sampling variance.
+pub struct StochasticUniversal;
+
+impl ParentSelection for StochasticUniversal {
+    fn select(&self, pop_and_fitness: &[(Genotype, f64)], count: usize,
+              rng: &mut ChaCha8Rng) -> Vec<usize> {
+        let weights = inverse_lengths(pop_and_fitness).collect::<Vec<f64>>();
+        let spacing = weights.iter().sum::<f64>() / count as f64;
+        let mut pointer = rng.gen_range(0.0..spacing);
+
+        let mut selected = Vec::with_capacity(count);
+        let mut cumulative = 0.0;
+        for (idx, weight) in weights.iter().enumerate() {
+            cumulative += weight;
+            while selected.len() < count && pointer < cumulative {
+                selected.push(idx);
+                pointer += spacing;
+            }
+        }
+        // Rounding may leave the last pointer just past the end of the wheel.
+        while selected.len() < count {
+            selected.push(weights.len() - 1);
+        }
+
+        // Otherwise parents would always be paired in order of fitness.
+        selected.shuffle(rng);
+        selected
+    }
+}
+
+/// Returns the probability that the individual with the given rank is
+/// selected under linear ranking, where rank N-1 is the best individual.
+pub fn probability_by_rank(s: f64, rank: usize, pop_size: usize) -> f64 {
+    let rank = rank as f64;
+    let pop_size = pop_size as f64;
+    (2.0 - s) / pop_size +
+        rank * (2.0 * s - 2.0) / (pop_size.powf(2.0) - pop_size)
+}
+
+fn inverse_lengths(pop_and_fitness: &[(Genotype, f64)])
+-> impl Iterator<Item = f64> + '_ {
+    pop_and_fitness.iter().map(|(_, fitness)| 1.0 / fitness)
+}
+
+fn sample_weighted(weights: Vec<f64>, count: usize, rng: &mut ChaCha8Rng) -> Vec<usize> {
+    let dist = WeightedIndex::new(weights).unwrap();
+    (0..count).map(|_| dist.sample(rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn population(pop_size: usize) -> Vec<(Genotype, f64)> {
+        let mut rng = ChaCha8Rng::from_entropy();
+        (0..pop_size)
+            .map(|i| (Genotype::random(5, &mut rng), (i + 1) as f64))
+            .collect()
+    }
+
+    #[test]
+    fn schemes_favor_fitter_individuals() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let pop_and_fitness = population(10);
+        for spec in ["rank:2.0", "tournament:3", "exponential:0.8",
+                     "proportional", "sus"] {
+            let scheme = parse(spec).unwrap();
+            let prepared = Prepared::new(scheme.as_ref(), 10);
+            for selected in [scheme.select(&pop_and_fitness, 1000, &mut rng),
+                             prepared.select(&pop_and_fitness, 1000, &mut rng)] {
+                assert_eq!(1000, selected.len());
+                let best = selected.iter().filter(|&&idx| idx == 0).count();
+                let worst = selected.iter().filter(|&&idx| idx == 9).count();
+                assert!(best > worst, "{} did not favor the best", spec);
+            }
+        }
+    }
+
+    #[test]
+    fn sus_selects_close_to_expected_counts() {
+        let mut rng = ChaCha8Rng::from_entropy();
+        let pop_and_fitness = population(2);
+        // Weights are 1 and 1/2, so out of 6 selections expect 4 and 2.
+        let selected = StochasticUniversal.select(&pop_and_fitness, 6, &mut rng);
+
+        assert_eq!(4, selected.iter().filter(|&&idx| idx == 0).count());
+    }
+
+    #[test]
+    fn invalid_schemes_are_rejected() {
+        assert!(parse("rank:3.0").is_err());
+        assert!(parse("tournament").is_err());
+        assert!(parse("exponential:1.5").is_err());
+        assert!(parse("roulette").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::time::Instant;
+
+use crate::config::{Algorithm, Config};
+use crate::diversity::Diversity;
+use crate::exact;
+use crate::genotype::Genotype;
+use crate::local_search::{self, LocalSearch};
+use crate::observer::{Event, Observer};
+use crate::result::RunResult;
+use crate::termination::{StopReason, Termination};
+use crate::weighted_graph::WeightedGraph;
+use crate::{annealing, ant_colony, construction, island, iterated_local_search, tabu};
+
+/// How long a solver may run: at most num_evals evaluations, and less if
+/// any of the termination conditions is met first. What counts as an
+/// evaluation depends on the solver.
+#[derive(Clone, Debug)]
+pub struct Budget {
+    pub num_evals: usize,
+    pub termination: Termination,
+}
+
+impl Budget {
+    /// Returns the budget given by the config's evaluations and stop
+    /// conditions.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            num_evals: config.num_evals,
+            termination: config.termination.clone(),
+        }
+    }
+}
+
+/// An algorithm that finds short tours, so that different algorithms can be
+/// run and compared in the same way.
+pub trait Solver {
+    fn name(&self) -> &'static str;
+
+    /// Searches for a short tour of the graph within the budget, reporting
+    /// what happens to the observer, which may stop the search early.
+    fn solve(&self, graph: &WeightedGraph, budget: &Budget,
+             observer: &mut dyn Observer) -> RunResult;
+}
+
+/// A config is a solver: the algorithm it selects, with its options.
+impl Solver for Config {
+    fn name(&self) -> &'static str {
+        self.algorithm.name()
+    }
+
+    fn solve(&self, graph: &WeightedGraph, budget: &Budget,
+             observer: &mut dyn Observer) -> RunResult {
+        match self.algorithm {
+            Algorithm::Genetic if self.islands > 1 =>
+                island::evolve_islands(self, graph, budget, observer),
+            Algorithm::Genetic => crate::evolve(self, graph, budget, observer),
+            Algorithm::Annealing => annealing::anneal(self, graph, budget, observer),
+            Algorithm::AntColony => ant_colony::ant_colony(self, graph, budget, observer),
+            Algorithm::Tabu => tabu::tabu_search(self, graph, budget, observer),
+            Algorithm::IteratedLocalSearch =>
+                iterated_local_search::iterated_local_search(self, graph, budget, observer),
+        }
+    }
+}
+
+/// The Held-Karp dynamic program, for instances of up to
+/// exact::MAX_DYNAMIC_PROGRAMMING_CITIES cities, which ignores the budget.
+/// Larger instances are solved by branch and bound within the budget instead.
+pub struct DynamicProgramming;
+
+impl Solver for DynamicProgramming {
+    fn name(&self) -> &'static str {
+        "dynamic-programming"
+    }
+
+    fn solve(&self, graph: &WeightedGraph, budget: &Budget,
+             observer: &mut dyn Observer) -> RunResult {
+        let start = Instant::now();
+        let (tour, length) = match exact::dynamic_programming(graph) {
+            Ok(solution) => solution,
+            Err(_) => return BranchAndBound { initial_tour: None }.solve(graph, budget, observer),
+        };
+        observer.notify(&Event::NewBest { evals: 0, tour: &tour, length });
+        crate::notify_stop(observer, 0, StopReason::Optimal, length, Some(length));
+        optimal_result(tour, length, 0, start)
+    }
+}
+
+/// Branch and bound from the tour given, or if none, a nearest neighbor tour
+/// improved by 2-opt and Or-opt. Each node of the search tree costs one
+/// evaluation, and of the termination conditions only the time limit
+/// applies. An observer that asks to stop cancels the search.
+pub struct BranchAndBound {
+    pub initial_tour: Option<Vec<usize>>,
+}
+
+impl Solver for BranchAndBound {
+    fn name(&self) -> &'static str {
+        "branch-and-bound"
+    }
+
+    fn solve(&self, graph: &WeightedGraph, budget: &Budget,
+             observer: &mut dyn Observer) -> RunResult {
+        let start = Instant::now();
+        let initial_tour = match &self.initial_tour {
+            Some(tour) => tour.clone(),
+            None if graph.num_vertices() == 0 => Vec::new(),
+            None => {
+                let mut tour = construction::nearest_neighbor(graph, 0);
+                let candidates = graph.nearest_neighbors(local_search::DEFAULT_CANDIDATES);
+                LocalSearch::TwoOptOrOpt.apply(&mut tour, graph, &candidates);
+                tour
+            }
+        };
+
+        let result = exact::branch_and_bound(
+            graph, &initial_tour, budget.termination.time_limit, budget.num_evals, observer
+        );
+        let time_limit = budget.termination.time_limit;
+        let stop_reason = if result.optimal {
+            StopReason::Optimal
+        } else if result.nodes >= budget.num_evals {
+            StopReason::Evaluations
+        } else if time_limit.is_some_and(|limit| result.elapsed >= limit) {
+            StopReason::TimeLimit
+        } else {
+            StopReason::Cancelled
+        };
+        crate::notify_stop(observer, result.nodes, stop_reason, result.best_length,
+                           Some(result.lower_bound));
+        if result.optimal {
+            return optimal_result(result.best_tour, result.best_length, result.nodes, start);
+        }
+        RunResult {
+            diversity: Diversity::measure(&[tour_and_length(&result.best_tour, result.best_length)]),
+            best_tour: result.best_tour,
+            best_length: result.best_length,
+            evaluations: result.nodes,
+            elapsed: start.elapsed(),
+            stop_reason,
+            lower_bound: Some(result.lower_bound),
+        }
+    }
+}
+
+fn tour_and_length(tour: &[usize], length: f64) -> (Genotype, f64) {
+    (Genotype::from_data(tour.to_vec()), length)
+}
+
+/// The result of a solver that proved its tour optimal, which is therefore
+/// its own lower bound.
+fn optimal_result(tour: Vec<usize>, length: f64, evaluations: usize, start: Instant) -> RunResult {
+    RunResult {
+        diversity: Diversity::measure(&[tour_and_length(&tour, length)]),
+        best_tour: tour,
+        best_length: length,
+        evaluations,
+        elapsed: start.elapsed(),
+        stop_reason: StopReason::Optimal,
+        lower_bound: Some(length),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observer::{Signal, Silent};
+    use crate::weighted_graph;
+
+    #[test]
+    fn solvers_are_interchangeable() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(10, 1));
+
+        let mut solvers: Vec<Box<dyn Solver>> = vec![
+            Box::new(DynamicProgramming),
+            Box::new(BranchAndBound { initial_tour: None }),
+        ];
+        for algorithm in Algorithm::ALL {
+            let args = [
+                "tsp", "unused", "10", "2000", "--seed", "1", "--algorithm", algorithm.name(),
+            ];
+            solvers.push(Box::new(Config::from_args(args.map(String::from).into_iter()).unwrap()));
+        }
+
+        let budget = Budget {
+            num_evals: 2000,
+            termination: Termination::default(),
+        };
+        let mut optimum = None;
+        for solver in solvers.iter() {
+            let mut reports = 0;
+            let result = solver.solve(&graph, &budget, &mut |event: &Event| {
+                if let Event::NewBest { .. } = event {
+                    reports += 1;
+                }
+                Signal::Continue
+            });
+            // The first solver is exact.
+            let optimum_length = *optimum.get_or_insert(result.best_length);
+
+            assert!(reports > 0, "{}", solver.name());
+            assert!(result.best_length >= optimum_length - 1e-9, "{}", solver.name());
+            assert!(result.best_length <= optimum_length * 1.1, "{}", solver.name());
+        }
+    }
+
+    #[test]
+    fn dynamic_programming_falls_back_on_large_instances() {
+        let points = (0..exact::MAX_DYNAMIC_PROGRAMMING_CITIES + 5)
+            .map(|i| ((i * 17 % 31) as f64, (i * i % 37) as f64))
+            .collect::<Vec<(f64, f64)>>();
+        let graph = WeightedGraph::from_points(points);
+        let budget = Budget {
+            num_evals: 100,
+            termination: Termination::default(),
+        };
+
+        let result = DynamicProgramming.solve(&graph, &budget, &mut Silent);
+        assert_eq!(graph.num_vertices(), result.best_tour.len());
+        assert!(result.evaluations <= 100);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::config::Config;
+use crate::diversity::Diversity;
+use crate::genotype::Move;
+use crate::observer::{Event, Observer, Statistics};
+use crate::result::RunResult;
+use crate::solver::Budget;
+use crate::termination::StopReason;
+use crate::weighted_graph::WeightedGraph;
+
+/// The moves tabu search considers from the current tour, each restricted
+/// to moves that make a city adjacent to one of its candidates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Neighborhood {
+    /// Replaces two edges with two others by reversing the path between
+    /// them.
+    TwoOpt,
+    /// Swaps the positions of two cities.
+    Swap,
+}
+
+impl Neighborhood {
+    pub const ALL: [Self; 2] = [Self::TwoOpt, Self::Swap];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|neighborhood| neighborhood.name() == name.trim())
+            .ok_or("unknown neighborhood")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TwoOpt => "2-opt",
+            Self::Swap => "swap",
+        }
+    }
+
+    /// Returns the moves that join city a to city c, given each city's
+    /// position in the tour.
+    fn moves(self, tour: &[usize], position: &[usize], a: usize, c: usize) -> Vec<Move> {
+        let num_vertices = tour.len();
+        let (i, j) = (position[a], position[c]);
+        match self {
+            Self::TwoOpt => {
+                // Removing the edges starting at positions k1 < k2 and
+                // reversing the path between them joins a to c, whether the
+                // edges are those after a and c or those before them.
+                let after = (i, j);
+                let before = ((i + num_vertices - 1) % num_vertices,
+                              (j + num_vertices - 1) % num_vertices);
+                [after, before]
+                    .into_iter()
+                    .map(|(k1, k2)| (k1.min(k2), k1.max(k2)))
+                    .filter(|&(k1, k2)| k2 - k1 >= 2)
+                    .map(|(k1, k2)| Move::Inversion(k1 + 1, k2 + 1))
+                    .collect()
+            }
+            Self::Swap => {
+                // Moving a next to c, on either side.
+                [(j + 1) % num_vertices, (j + num_vertices - 1) % num_vertices]
+                    .into_iter()
+                    .filter(|&k| k != i)
+                    .map(|k| Move::Swap(i, k))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// When a tabu move may be made anyway.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aspiration {
+    Never,
+    /// If it leads to a tour shorter than the best so far.
+    Best,
+    /// If it shortens the current tour.
+    Improving,
+}
+
+impl Aspiration {
+    pub const ALL: [Self; 3] = [Self::Never, Self::Best, Self::Improving];
+
+    pub fn parse(name: &str) -> Result<Self, &'static str> {
+        Self::ALL
+            .into_iter()
+            .find(|aspiration| aspiration.name() == name.trim())
+            .ok_or("unknown aspiration criterion")
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Never => "none",
+            Self::Best => "best",
+            Self::Improving => "improving",
+        }
+    }
+}
+
+/// Edges of a tour, each with its smaller city first.
+type Edges = Vec<(usize, usize)>;
+
+/// Returns the edges a move would remove from the tour and those it would
+/// add.
+fn changed_edges(tour: &[usize], mv: Move) -> (Edges, Edges) {
+    let num_vertices = tour.len();
+    let at = |pos: usize| tour[pos % num_vertices];
+    let edge = |v: usize, u: usize| (v.min(u), v.max(u));
+
+    let (old, new) = match mv {
+        Move::Inversion(pos1, pos2) if pos2 - pos1 < 2 || pos2 - pos1 == num_vertices =>
+            (Vec::new(), Vec::new()),
+        Move::Inversion(pos1, pos2) => {
+            let (before, first) = (at(pos1 + num_vertices - 1), tour[pos1]);
+            let (last, after) = (tour[pos2 - 1], at(pos2));
+            (vec![edge(before, first), edge(last, after)],
+             vec![edge(before, last), edge(first, after)])
+        }
+        Move::Swap(pos1, pos2) => {
+            let swapped = |pos: usize| match pos % num_vertices {
+                p if p == pos1 => tour[pos2],
+                p if p == pos2 => tour[pos1],
+                p => tour[p],
+            };
+            let mut starts = [pos1, pos2, pos1 + num_vertices - 1, pos2 + num_vertices - 1]
+                .map(|pos| pos % num_vertices)
+                .to_vec();
+            starts.sort();
+            starts.dedup();
+            (starts.iter().map(|&pos| edge(at(pos), at(pos + 1))).collect(),
+             starts.iter().map(|&pos| edge(swapped(pos), swapped(pos + 1))).collect())
+        }
+        Move::Insert(..) => panic!("tabu search does not use insert moves"),
+    };
+    let removed = old.iter().copied().filter(|e| !new.contains(e)).collect();
+    let added = new.iter().copied().filter(|e| !old.contains(e)).collect();
+    (removed, added)
+}
+
+/// Runs tabu search on the given graph from a single tour, built as the
+/// GA would build the first member of its initial population. Each
+/// iteration prices every move in the configured neighborhoods that joins a
+/// city to one of its candidates, costing one evaluation per move, and
+/// makes the best admissible one even if it lengthens the tour. Edges a
+/// move removes may not be added back for the next `tenure` iterations,
+/// unless the aspiration criterion allows it.
+pub fn tabu_search(config: &Config, graph: &WeightedGraph, budget: &Budget,
+                   observer: &mut dyn Observer) -> RunResult {
+    let mut rng = config.rng();
+    let num_vertices = graph.num_vertices();
+    let num_evals = budget.num_evals;
+    let candidates = graph.nearest_neighbors(config.candidates);
+
+    let mut current = crate::initial_population(
+        1, num_vertices, &config.seeding, graph, &mut rng
+    ).remove(0);
+    let mut best = current.clone();
+    let mut position = vec![0; num_vertices];
+    // The iteration until which adding each edge is tabu.
+    let mut tabu_until = vec![0; num_vertices * num_vertices];
+
+    let criteria = crate::termination_criteria(config, budget, graph, best.1, observer);
+    let mut termination = criteria.start(num_evals, best.1);
+    crate::notify_new_best(observer, &mut termination, 0, &best);
+    let mut iteration = 0;
+    let mut evals = 0;
+    let stop_reason: StopReason;
+    'search: loop {
+        if let Some(reason) = termination.check(evals, best.1) {
+            stop_reason = reason;
+            break;
+        }
+        iteration += 1;
+
+        let tour = current.0.data();
+        for (i, &city) in tour.iter().enumerate() {
+            position[city] = i;
+        }
+
+        let prev_evals = evals;
+        let mut chosen: Option<(Move, f64)> = None;
+        'scan: for (a, neighbors) in candidates.iter().enumerate() {
+            for &c in neighbors.iter() {
+                for neighborhood in config.neighborhood.iter() {
+                    for mv in neighborhood.moves(tour, &position, a, c) {
+                        if evals == num_evals {
+                            break 'scan;
+                        }
+                        evals += 1;
+                        let delta = current.0.move_delta(mv, graph);
+                        if chosen.is_some_and(|(_, best_delta)| delta >= best_delta) {
+                            continue;
+                        }
+
+                        let (_, added) = changed_edges(tour, mv);
+                        if added.is_empty() {
+                            continue;
+                        }
+                        let is_tabu = added.iter()
+                            .any(|&(v, u)| tabu_until[v * num_vertices + u] > iteration);
+                        let aspires = match config.aspiration {
+                            Aspiration::Never => false,
+                            Aspiration::Best => current.1 + delta < best.1,
+                            Aspiration::Improving => delta < 0.0,
+                        };
+                        if !is_tabu || aspires {
+                            chosen = Some((mv, delta));
+                        }
+                    }
+                }
+            }
+        }
+
+        let Some((mv, delta)) = chosen else {
+            if evals == prev_evals {
+                // There are no moves, as in tours of fewer than four cities.
+                stop_reason = StopReason::Evaluations;
+                break 'search;
+            }
+            // Every move is tabu, so wait for the tabu list to clear.
+            continue;
+        };
+        let (removed, _) = changed_edges(current.0.data(), mv);
+        for (v, u) in removed {
+            tabu_until[v * num_vertices + u] = iteration + config.tenure;
+        }
+        current.0.apply_move(mv);
+        current.1 += delta;
+        if current.1 < best.1 {
+            // Recompute the length of new best tours so that rounding errors
+            // in the deltas do not accumulate.
+            current.1 = crate::fitness(graph, current.0.data());
+            best = current.clone();
+            crate::notify_new_best(observer, &mut termination, evals, &best);
+        }
+
+        if evals / 10 > prev_evals / 10 {
+            let best = best.1;
+            crate::notify(observer, &mut termination, Event::Progress { evals, best });
+        }
+        let interval = config.diversity_interval;
+        if interval > 0 && evals / interval > prev_evals / interval {
+            let statistics = Statistics::Tabu { iteration, current: current.1 };
+            crate::notify(observer, &mut termination, Event::Statistics { evals, statistics });
+        }
+    }
+
+    crate::notify_stop(observer, evals, stop_reason, best.1, criteria.lower_bound);
+
+    RunResult {
+        best_tour: best.0.data().to_vec(),
+        best_length: best.1,
+        evaluations: evals,
+        elapsed: termination.elapsed(),
+        stop_reason,
+        diversity: Diversity::measure(&[best]),
+        lower_bound: criteria.lower_bound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha8Rng;
+    use crate::observer::Silent;
+    use crate::genotype::Genotype;
+    use crate::weighted_graph;
+    use rand::prelude::*;
+
+    #[test]
+    fn changed_edges_match_the_tours() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let tour_edges = |tour: &[usize]| {
+            let mut edges = (0..tour.len())
+                .map(|i| {
+                    let (v, u) = (tour[i], tour[(i + 1) % tour.len()]);
+                    (v.min(u), v.max(u))
+                })
+                .collect::<Vec<(usize, usize)>>();
+            edges.sort();
+            edges
+        };
+
+        for _ in 0..200 {
+            let genotype = Genotype::random(8, &mut rng);
+            let (pos1, pos2) = (rng.gen_range(0..8), rng.gen_range(0..8));
+            let inversion = Move::Inversion(pos1.min(pos2), pos1.max(pos2) + 1);
+            for mv in [Move::Swap(pos1, pos2), inversion] {
+                let mut moved = genotype.clone();
+                moved.apply_move(mv);
+                let (old, new) = (tour_edges(genotype.data()), tour_edges(moved.data()));
+
+                let (removed, added) = changed_edges(genotype.data(), mv);
+                let mut expected = old.clone();
+                expected.retain(|e| !removed.contains(e));
+                expected.extend(added);
+                expected.sort();
+                assert_eq!(new, expected, "{:?} {:?}", genotype, mv);
+            }
+        }
+    }
+
+    #[test]
+    fn tabu_search_improves_on_a_random_tour() {
+        let graph = WeightedGraph::from_points(weighted_graph::random_points(30, 1));
+
+        for neighborhood in ["2-opt", "swap", "2-opt,swap"] {
+            let args = [
+                "tsp", "unused", "2", "20000", "--seed", "1", "--neighborhood", neighborhood,
+            ];
+            let config = Config::from_args(args.map(String::from).into_iter()).unwrap();
+            let budget = Budget::from_config(&config);
+            let result = tabu_search(&config, &graph, &budget, &mut Silent);
+
+            assert_eq!(20000, result.evaluations);
+            assert!((crate::fitness(&graph, &result.best_tour) - result.best_length).abs() < 1e-6);
+            // A random tour of 30 uniform points is about 1500 long.
+            assert!(result.best_length < 900.0);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Why a run stopped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    /// The evaluation budget was used up.
+    Evaluations,
+    TimeLimit,
+    /// A tour at least as short as the target length was found.
+    TargetLength,
+    /// The best tour came within the allowed gap of the lower bound.
+    LowerBoundGap,
+    /// The best tour did not improve for the allowed number of evaluations.
+    Stagnation,
+    /// The cancellation flag was raised, or an observer stopped the run.
+    Cancelled,
+    /// An exact solver proved the best tour optimal.
+    Optimal,
+}
+
+impl StopReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Evaluations => "evaluations",
+            Self::TimeLimit => "time-limit",
+            Self::TargetLength => "target-length",
+            Self::LowerBoundGap => "lower-bound-gap",
+            Self::Stagnation => "stagnation",
+            Self::Cancelled => "cancelled",
+            Self::Optimal => "optimal",
+        }
+    }
+}
+
+/// Stop conditions besides the evaluation budget, any of which ends the run.
+/// Conditions left as None are not checked.
+#[derive(Clone, Debug, Default)]
+pub struct Termination {
+    pub time_limit: Option<Duration>,
+    pub target_length: Option<f64>,
+    /// A lower bound on the optimal tour length, used with max_gap.
+    pub lower_bound: Option<f64>,
+    /// The largest acceptable gap to the lower bound, as a percentage.
+    pub max_gap: Option<f64>,
+    /// Evaluations without improvement after which the run stops.
+    pub max_stall: Option<usize>,
+    /// Stops the run once set to true, for example from another thread.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Termination {
+    /// Starts the clock for a run that is to stop after at most num_evals
+    /// evaluations.
+    pub fn start(&self, num_evals: usize, initial_best: f64) -> TerminationCheck<'_> {
+        TerminationCheck {
+            criteria: self,
+            num_evals,
+            start: Instant::now(),
+            best: initial_best,
+            last_improvement: 0,
+            stopped: false,
+        }
+    }
+}
+
+/// Returns the percentage by which length exceeds the lower bound.
+pub fn gap(length: f64, lower_bound: f64) -> f64 {
+    (length - lower_bound) / lower_bound * 100.0
+}
+
+/// Tracks the progress of a single run against its stop conditions.
+pub struct TerminationCheck<'a> {
+    criteria: &'a Termination,
+    num_evals: usize,
+    start: Instant,
+    best: f64,
+    last_improvement: usize,
+    stopped: bool,
+}
+
+impl TerminationCheck<'_> {
+    /// Records the best tour length after `evals` evaluations and returns
+    /// the reason to stop, if any.
+    pub fn check(&mut self, evals: usize, best: f64) -> Option<StopReason> {
+        if best < self.best {
+            self.best = best;
+            self.last_improvement = evals;
+        }
+        let criteria = self.criteria;
+
+        let cancelled = criteria.cancel.as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+        if self.stopped || cancelled {
+            return Some(StopReason::Cancelled);
+        }
+        if criteria.target_length.is_some_and(|target| best <= target) {
+            return Some(StopReason::TargetLength);
+        }
+        if let (Some(bound), Some(max_gap)) = (criteria.lower_bound, criteria.max_gap) {
+            if gap(best, bound) <= max_gap {
+                return Some(StopReason::LowerBoundGap);
+            }
+        }
+        if evals >= self.num_evals {
+            return Some(StopReason::Evaluations);
+        }
+        if criteria.time_limit.is_some_and(|limit| self.elapsed() >= limit) {
+            return Some(StopReason::TimeLimit);
+        }
+        if criteria.max_stall.is_some_and(|stall| evals - self.last_improvement >= stall) {
+            return Some(StopReason::Stagnation);
+        }
+        None
+    }
+
+    /// Cancels the run from within, as an observer does, so that the next
+    /// check stops it.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Returns the fraction of the run's budget used so far: the larger of the
+    /// fractions of evaluations and, if there is a time limit, of time.
+    pub fn progress(&self, evals: usize) -> f64 {
+        let eval_fraction = evals as f64 / self.num_evals as f64;
+        match self.criteria.time_limit {
+            None => eval_fraction,
+            Some(limit) => eval_fraction
+                .max(self.elapsed().as_secs_f64() / limit.as_secs_f64()),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Returns the best length and the evaluation at which it last improved,
+    /// as saved in a checkpoint.
+    pub fn state(&self) -> (f64, usize) {
+        (self.best, self.last_improvement)
+    }
+
+    /// Restores the state saved by `state`, with the time the run had
+    /// already taken counting towards its time limit.
+    pub fn restore(&mut self, (best, last_improvement): (f64, usize), elapsed: Duration) {
+        self.best = best;
+        self.last_improvement = last_improvement;
+        self.start = Instant::now().checked_sub(elapsed).unwrap_or(self.start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_met_condition_is_reported() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let termination = Termination {
+            target_length: Some(50.0),
+            lower_bound: Some(80.0),
+            max_gap: Some(5.0),
+            max_stall: Some(100),
+            cancel: Some(cancel.clone()),
+            ..Termination::default()
+        };
+        let mut check = termination.start(1000, 200.0);
+
+        assert_eq!(None, check.check(10, 150.0));
+        assert_eq!(Some(StopReason::Stagnation), check.check(110, 150.0));
+        assert_eq!(Some(StopReason::LowerBoundGap), check.check(120, 84.0));
+        assert_eq!(Some(StopReason::TargetLength), check.check(130, 50.0));
+        assert_eq!(Some(StopReason::Evaluations), check.check(1000, 150.0));
+
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(Some(StopReason::Cancelled), check.check(140, 150.0));
+
+        let termination = Termination::default();
+        let mut check = termination.start(1000, 200.0);
+        check.stop();
+        assert_eq!(Some(StopReason::Cancelled), check.check(10, 150.0));
+    }
+
+    #[test]
+    fn progress_uses_the_larger_fraction() {
+        let termination = Termination {
+            time_limit: Some(Duration::from_secs(3600)),
+            ..Termination::default()
+        };
+        let check = termination.start(1000, 200.0);
+
+        assert!((check.progress(250) - 0.25).abs() < 1e-3);
+        assert_eq!(None, termination.start(1000, 200.0).check(999, 200.0));
+    }
+}
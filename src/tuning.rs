@@ -0,0 +1,254 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::bench::{self, Instance, Setup};
+
+/// The ranges the tuner samples the GA's parameters from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Space {
+    pub pop_size: (usize, usize),
+    /// Selection pressure of linear rank parent selection.
+    pub pressure: (f64, f64),
+    /// Percentage of the budget after which the schedule switches from swap
+    /// to inversion mutation.
+    pub switch_point: (f64, f64),
+}
+
+impl Default for Space {
+    fn default() -> Self {
+        Self {
+            pop_size: (10, 200),
+            pressure: (1.1, 2.0),
+            switch_point: (0.0, 50.0),
+        }
+    }
+}
+
+impl Space {
+    /// Parses a range of the form `min:max`.
+    pub fn parse_range<T: std::str::FromStr + PartialOrd>(spec: &str)
+    -> Result<(T, T), &'static str> {
+        let (min, max) = spec.split_once(':')
+            .ok_or("ranges must be of the form min:max")?;
+        let min = min.trim().parse::<T>().map_err(|_| "could not parse range")?;
+        let max = max.trim().parse::<T>().map_err(|_| "could not parse range")?;
+        if min > max {
+            return Err("range minimum exceeds its maximum");
+        }
+        Ok((min, max))
+    }
+
+    /// Returns an error if the space includes invalid parameter values.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.pop_size.0 < 2 {
+            return Err("pop sizes must be at least 2");
+        }
+        if self.pressure.0 <= 1.0 || self.pressure.1 > 2.0 {
+            return Err("selection pressures must be in (1, 2]");
+        }
+        if self.switch_point.0 < 0.0 || self.switch_point.1 > 100.0 {
+            return Err("switch points must be in [0, 100]");
+        }
+        Ok(())
+    }
+
+    /// Samples the pop size log-uniformly and the others uniformly, with
+    /// the pressure rounded to 2 decimals and the switch point to 1.
+    pub fn sample(&self, rng: &mut ChaCha8Rng) -> Candidate {
+        let (min, max) = self.pop_size;
+        let pop_size = rng.gen_range((min as f64).ln()..=(max as f64).ln()).exp().round();
+        let round = |value: f64, decimals: i32| {
+            let scale = 10f64.powi(decimals);
+            (value * scale).round() / scale
+        };
+        Candidate {
+            pop_size: (pop_size as usize).clamp(min, max),
+            pressure: round(rng.gen_range(self.pressure.0..=self.pressure.1), 2),
+            switch_point: round(rng.gen_range(self.switch_point.0..=self.switch_point.1), 1),
+        }
+    }
+}
+
+/// One setting of the tuned parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    pub pop_size: usize,
+    pub pressure: f64,
+    pub switch_point: f64,
+}
+
+impl Candidate {
+    /// The config keys the candidate sets.
+    pub const KEYS: [&'static str; 3] = ["pop_size", "parent_selection", "schedule"];
+
+    /// Returns the candidate as config options, in the order they are applied.
+    pub fn options(&self) -> [(&'static str, String); 3] {
+        let [pop_size, parent_selection, schedule] = Self::KEYS;
+        [
+            (pop_size, self.pop_size.to_string()),
+            (parent_selection, format!("rank:{}", self.pressure)),
+            (schedule, format!("swap,edge,rank@{}%;inversion,edge,worst", self.switch_point)),
+        ]
+    }
+
+    /// Returns the candidate as the contents of a config file.
+    pub fn config_file(&self) -> String {
+        self.options()
+            .iter()
+            .map(|(key, value)| format!("{} = {}\n", key, value))
+            .collect()
+    }
+}
+
+/// A candidate with its score in a round of tuning, lower being better.
+pub type Scored = (Candidate, f64);
+
+/// The result of tuning: the best candidate and its score, the mean ratio of
+/// its tour lengths to the best length any surviving candidate found on the
+/// same instance and seed.
+#[derive(Clone, Copy, Debug)]
+pub struct Tuned {
+    pub best: Candidate,
+    pub score: f64,
+    pub runs: usize,
+}
+
+/// Tunes the GA's parameters on the training instances by random search with
+/// successive halving. Every run uses `num_evals` evaluations and the given
+/// options, written as on the command line, with each candidate's options
+/// after them. The options may not set the tuned parameters themselves.
+///
+/// The candidates sampled from the space all run on one task per instance.
+/// After each round the better half by score survives, and runs on twice as
+/// many tasks, those of the previous rounds included, cycling through the
+/// instances with a new seed each time round. Tasks use seeds `seed`,
+/// `seed + 1`, ... if a seed is given. Each round is reported with its number
+/// of tasks and its survivors, best first.
+pub fn tune(instances: &[Instance], num_evals: usize, options: &[String], space: &Space,
+            num_candidates: usize, seed: Option<u64>,
+            report: &mut dyn FnMut(usize, usize, &[Scored]))
+-> Result<Tuned, &'static str> {
+    if instances.is_empty() || num_candidates == 0 {
+        return Err("tuning requires instances and candidates");
+    }
+    space.validate()?;
+    let tuned_keys = Candidate::KEYS.map(|key| format!("--{}", key));
+    if options.iter().step_by(2).any(|option| tuned_keys.contains(option)) {
+        return Err("tuning options may not include pop_size, parent_selection or schedule");
+    }
+    let mut rng = match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+
+    // Each survivor's tour lengths on the tasks run so far, in task order.
+    let mut survivors = (0..num_candidates)
+        .map(|_| (space.sample(&mut rng), Vec::new()))
+        .collect::<Vec<(Candidate, Vec<f64>)>>();
+    let mut num_tasks = instances.len();
+    let mut runs = 0;
+    let mut round = 0;
+    loop {
+        for (candidate, lengths) in survivors.iter_mut() {
+            for task in lengths.len()..num_tasks {
+                let seed = seed.map(|seed| seed.wrapping_add(task as u64));
+                let setup = task_setup(candidate, num_evals, options, seed);
+                let instance = &instances[task % instances.len()];
+                let summary = bench::bench(instance, &setup, 1, None, 0.0, &mut |_, _| ())?;
+                lengths.push(summary.best);
+                runs += 1;
+            }
+        }
+
+        let best_lengths = (0..num_tasks)
+            .map(|task| survivors
+                .iter()
+                .map(|(_, lengths)| lengths[task])
+                .fold(f64::INFINITY, f64::min))
+            .collect::<Vec<f64>>();
+        let scores = survivors
+            .iter()
+            .map(|(_, lengths)| {
+                let ratios = lengths.iter().zip(best_lengths.iter());
+                ratios.map(|(length, best)| length / best).sum::<f64>() / num_tasks as f64
+            })
+            .collect::<Vec<f64>>();
+        let mut order = (0..survivors.len()).collect::<Vec<usize>>();
+        order.sort_by(|&a, &b| scores[a].total_cmp(&scores[b]));
+        let scored = order
+            .iter()
+            .map(|&idx| (survivors[idx].0, scores[idx]))
+            .collect::<Vec<Scored>>();
+        report(round, num_tasks, &scored);
+
+        if survivors.len() == 1 {
+            let (best, score) = scored[0];
+            return Ok(Tuned { best, score, runs });
+        }
+        let keep = survivors.len().div_ceil(2);
+        survivors = order[..keep].iter().map(|&idx| survivors[idx].clone()).collect();
+        num_tasks *= 2;
+        round += 1;
+    }
+}
+
+fn task_setup(candidate: &Candidate, num_evals: usize, options: &[String], seed: Option<u64>)
+-> Setup {
+    let mut args = vec![candidate.pop_size.to_string(), num_evals.to_string()];
+    args.extend_from_slice(options);
+    for (key, value) in candidate.options() {
+        args.push(format!("--{}", key));
+        args.push(value);
+    }
+    if let Some(seed) = seed {
+        args.extend([String::from("--seed"), seed.to_string()]);
+    }
+    Setup { name: String::from("candidate"), args }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::weighted_graph::WeightedGraph;
+
+    #[test]
+    fn tuning_halves_the_candidates_to_a_valid_config() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let instances = (0..2)
+            .map(|i| {
+                let points = (0..12)
+                    .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+                    .collect::<Vec<(f64, f64)>>();
+                let graph = WeightedGraph::from_points(points);
+                Instance { name: format!("random{}", i), graph }
+            })
+            .collect::<Vec<Instance>>();
+        let space = Space { pop_size: (5, 20), ..Space::default() };
+        let options = ["--diversity_interval", "0"].map(String::from);
+
+        let mut rounds = Vec::new();
+        let mut report = |_, tasks, survivors: &[Scored]| {
+            rounds.push((tasks, survivors.len()));
+        };
+        let tuned = tune(&instances, 500, &options, &space, 5, Some(3), &mut report).unwrap();
+
+        assert_eq!(vec![(2, 5), (4, 3), (8, 2), (16, 1)], rounds);
+        assert_eq!(5 * 2 + 3 * 2 + 2 * 4 + 8, tuned.runs);
+        assert!(tuned.score >= 1.0);
+        assert!((5..=20).contains(&tuned.best.pop_size));
+
+        let file_name = std::env::temp_dir()
+            .join(format!("tsp-tuned-test-{}.cfg", std::process::id()));
+        let file_name = file_name.to_str().unwrap();
+        std::fs::write(file_name, tuned.best.config_file()).unwrap();
+        let config = Config::from_args(
+            ["tsp", "unused", "50", "500", "--config", file_name].map(String::from).into_iter()
+        );
+        std::fs::remove_file(file_name).unwrap();
+        assert_eq!(tuned.best.pop_size, config.unwrap().pop_size);
+
+        let options = ["--schedule", "inversion,edge,worst"].map(String::from);
+        assert!(tune(&instances, 500, &options, &space, 5, Some(3), &mut |_, _, _| ()).is_err());
+    }
+}
@@ -1,10 +1,9 @@
 mod utils;
 
-use std::mem::swap;
-
 pub struct WeightedGraph {
     matrix: Vec<f64>,
     num_vertices: usize,
+    points: Vec<(f64, f64)>,
 }
 
 impl WeightedGraph {
@@ -39,6 +38,7 @@ impl WeightedGraph {
         WeightedGraph {
             matrix,
             num_vertices,
+            points: point_vec,
         }
     }
 
@@ -46,11 +46,77 @@ impl WeightedGraph {
         self.num_vertices
     }
 
+    /// Returns the points on the xy-plane the graph was built from.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
     /// Returns the label on the edge between vertices v and u.
-    pub fn weight_between(&self, mut v: usize, mut u: usize) -> f64 {
+    pub fn weight_between(&self, v: usize, u: usize) -> f64 {
         self.matrix[Self::flat_index(self.num_vertices, v, u)]
     }
 
+    /// Returns the edges of a minimum spanning tree, computed with Prim's
+    /// algorithm in O(n^2) time as suits a complete graph.
+    pub fn minimum_spanning_tree(&self) -> Vec<(usize, usize)> {
+        let vertices = (0..self.num_vertices).collect::<Vec<usize>>();
+        self.minimum_spanning_tree_of(&vertices, |v, u| self.weight_between(v, u))
+    }
+
+    /// Returns the edges of a minimum spanning tree over the given subset of
+    /// vertices, using the given weight function in place of the graph's own.
+    pub fn minimum_spanning_tree_of(&self, vertices: &[usize],
+                                    weight: impl Fn(usize, usize) -> f64)
+    -> Vec<(usize, usize)> {
+        let num_vertices = vertices.len();
+        if num_vertices == 0 {
+            return Vec::new();
+        }
+
+        let mut in_tree = vec![false; num_vertices];
+        // For each vertex outside the tree, its cheapest edge into the tree.
+        let mut cheapest = (0..num_vertices)
+            .map(|i| (weight(vertices[0], vertices[i]), 0))
+            .collect::<Vec<(f64, usize)>>();
+        in_tree[0] = true;
+
+        let mut edges = Vec::with_capacity(num_vertices - 1);
+        for _ in 1..num_vertices {
+            let i = (0..num_vertices)
+                .filter(|&i| !in_tree[i])
+                .min_by(|&x, &y| cheapest[x].0.partial_cmp(&cheapest[y].0).unwrap())
+                .unwrap();
+            in_tree[i] = true;
+            edges.push((vertices[cheapest[i].1], vertices[i]));
+
+            for j in 0..num_vertices {
+                let w = weight(vertices[i], vertices[j]);
+                if !in_tree[j] && w < cheapest[j].0 {
+                    cheapest[j] = (w, i);
+                }
+            }
+        }
+        edges
+    }
+
+    /// Returns, for each vertex, up to k other vertices ordered from nearest
+    /// to farthest. Restricting a search to these candidate lists skips
+    /// edges that are unlikely to be in a good tour.
+    pub fn nearest_neighbors(&self, k: usize) -> Vec<Vec<usize>> {
+        (0..self.num_vertices)
+            .map(|v| {
+                let mut others = (0..self.num_vertices)
+                    .filter(|&u| u != v)
+                    .collect::<Vec<usize>>();
+                others.sort_by(|&x, &y| {
+                    self.weight_between(v, x).partial_cmp(&self.weight_between(v, y)).unwrap()
+                });
+                others.truncate(k);
+                others
+            })
+            .collect()
+    }
+
     pub fn print(&self) {
         for i in 0..self.num_vertices {
             println!(
@@ -69,6 +135,17 @@ impl WeightedGraph {
 
 }
 
+/// Returns n points uniformly distributed over a 100 by 100 square, the same
+/// ones for the same seed, for tests to build instances from.
+#[cfg(test)]
+pub fn random_points(n: usize, seed: u64) -> Vec<(f64, f64)> {
+    use rand::prelude::*;
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+    (0..n)
+        .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,6 +159,7 @@ mod tests {
                 2.0, 1.0, 4.0,
                 3.0, 4.0, 1.0,
             ],
+            points: vec![],
         };
         assert_eq!(4.0, graph.weight_between(2, 1));
         assert_eq!(4.0, graph.weight_between(1, 2));
@@ -89,4 +167,21 @@ mod tests {
         assert_eq!(2.0, graph.weight_between(0, 1));
         assert_eq!(2.0, graph.weight_between(1, 0));
     }
+
+    #[test]
+    fn minimum_spanning_tree_is_computed_correctly() {
+        let graph = WeightedGraph::from_points(vec![
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (1.0, 0.0),
+            (10.0, 2.0),
+        ]);
+        let mut edges = graph.minimum_spanning_tree()
+            .into_iter()
+            .map(|(v, u)| (v.min(u), v.max(u)))
+            .collect::<Vec<(usize, usize)>>();
+        edges.sort();
+
+        assert_eq!(vec![(0, 2), (1, 2), (1, 3)], edges);
+    }
 }